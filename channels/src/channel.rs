@@ -0,0 +1,808 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::{self, Future};
+use std::mem;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use futures::executor::block_on;
+
+use crate::ring::{self, Consumer, Producer};
+
+/// Capacity of a channel under test
+#[derive(Debug, Clone, Copy)]
+pub enum Capacity {
+    Bounded(usize),
+    Unbounded,
+}
+
+impl fmt::Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capacity::Bounded(n) => write!(f, "{}", n),
+            Capacity::Unbounded => f.write_str("unbounded"),
+        }
+    }
+}
+
+impl FromStr for Capacity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unbounded" => Ok(Capacity::Unbounded),
+            n => n
+                .parse()
+                .map(Capacity::Bounded)
+                .map_err(|e| format!("Invalid capacity {}. Expected a number or unbounded. {}", n, e)),
+        }
+    }
+}
+
+/// A channel implementation under test. Every implementation is driven
+/// through the same `Tx`/`Rx` interface so that runners are shared
+pub trait Channel<T: Clone + Send + 'static> {
+    type Tx: Tx<T>;
+    type Rx: Rx<T>;
+
+    const NAME: &'static str;
+
+    /// Whether send and recv futures yield to the executor. Synchronous
+    /// channels block the thread in them and only run on plain threads
+    const ASYNC: bool;
+
+    /// Whether every receiver sees every message
+    const BROADCAST: bool = false;
+
+    /// Whether sends fail once every receiver is gone. Checked by the
+    /// conformance tests
+    #[cfg_attr(not(test), allow(dead_code))]
+    const SEND_FAILS_WITHOUT_RECEIVER: bool = true;
+
+    /// `None` when the channel doesn't support the capacity
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)>;
+
+    /// Another sender of the same channel. `None` for single producer
+    /// channels
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx>;
+
+    /// Another receiver of the same channel. `None` for single consumer
+    /// channels
+    fn receiver(tx: &Self::Tx, rx: &Self::Rx) -> Option<Self::Rx>;
+}
+
+/// Sending half. Sends fail with the message once every receiver is gone
+pub trait Tx<T>: Send + 'static {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send;
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T>;
+}
+
+/// Receiving half. Receives return `None` once the channel is closed and
+/// drained
+pub trait Rx<T: Send>: Send + 'static {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send;
+
+    fn recv_blocking(&mut self) -> Option<T>;
+
+    /// A message if one is ready. `None` both when empty and when closed
+    fn try_recv(&mut self) -> Option<T>;
+
+    /// Messages skipped because the receiver fell behind. Only broadcast
+    /// channels drop any
+    fn lost(&self) -> usize {
+        0
+    }
+
+    /// Waits for a message and appends it to `buf` along with whatever else
+    /// is ready, up to `max` in all. 0 once the channel is closed and drained
+    fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> impl Future<Output = usize> + Send {
+        async move {
+            match self.recv().await {
+                Some(msg) => buf.push(msg),
+                None => return 0,
+            }
+
+            1 + self.try_recv_many(buf, max - 1)
+        }
+    }
+
+    fn recv_many_blocking(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        match self.recv_blocking() {
+            Some(msg) => buf.push(msg),
+            None => return 0,
+        }
+
+        1 + self.try_recv_many(buf, max - 1)
+    }
+
+    /// Appends ready messages to `buf`, up to `max`, without waiting
+    fn try_recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        let mut n = 0;
+        while n < max {
+            match self.try_recv() {
+                Some(msg) => buf.push(msg),
+                None => break,
+            }
+
+            n += 1;
+        }
+
+        n
+    }
+}
+
+pub struct Crossbeam;
+
+impl<T: Clone + Send + 'static> Channel<T> for Crossbeam {
+    type Tx = crossbeam_channel::Sender<T>;
+    type Rx = crossbeam_channel::Receiver<T>;
+
+    const NAME: &'static str = "crossbeam";
+    const ASYNC: bool = false;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(n) => Some(crossbeam_channel::bounded(n)),
+            Capacity::Unbounded => Some(crossbeam_channel::unbounded()),
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, rx: &Self::Rx) -> Option<Self::Rx> {
+        Some(rx.clone())
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for crossbeam_channel::Sender<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        future::ready(self.send_blocking(msg))
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        crossbeam_channel::Sender::send(self, msg).map_err(|e| e.0)
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for crossbeam_channel::Receiver<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        future::ready(self.recv_blocking())
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        crossbeam_channel::Receiver::recv(self).ok()
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        crossbeam_channel::Receiver::try_recv(self).ok()
+    }
+}
+
+pub struct Flume;
+
+impl<T: Clone + Send + 'static> Channel<T> for Flume {
+    type Tx = flume::Sender<T>;
+    type Rx = flume::Receiver<T>;
+
+    const NAME: &'static str = "flume";
+    const ASYNC: bool = true;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(n) => Some(flume::bounded(n)),
+            Capacity::Unbounded => Some(flume::unbounded()),
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, rx: &Self::Rx) -> Option<Self::Rx> {
+        Some(rx.clone())
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for flume::Sender<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        let send = self.send_async(msg);
+        async move { send.await.map_err(|e| e.0) }
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        flume::Sender::send(self, msg).map_err(|e| e.0)
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for flume::Receiver<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        let recv = self.recv_async();
+        async move { recv.await.ok() }
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        flume::Receiver::recv(self).ok()
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        flume::Receiver::try_recv(self).ok()
+    }
+}
+
+pub struct AsyncChannel;
+
+impl<T: Clone + Send + 'static> Channel<T> for AsyncChannel {
+    type Tx = async_channel::Sender<T>;
+    type Rx = async_channel::Receiver<T>;
+
+    const NAME: &'static str = "async-channel";
+    const ASYNC: bool = true;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(0) => None,
+            Capacity::Bounded(n) => Some(async_channel::bounded(n)),
+            Capacity::Unbounded => Some(async_channel::unbounded()),
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, rx: &Self::Rx) -> Option<Self::Rx> {
+        Some(rx.clone())
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for async_channel::Sender<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        let send = async_channel::Sender::send(self, msg);
+        async move { send.await.map_err(|e| e.0) }
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        block_on(Tx::send(self, msg))
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for async_channel::Receiver<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        let recv = async_channel::Receiver::recv(self);
+        async move { recv.await.ok() }
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        block_on(Rx::recv(self))
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        async_channel::Receiver::try_recv(self).ok()
+    }
+}
+
+pub struct TokioMpsc;
+
+pub enum TokioTx<T> {
+    Bounded(tokio::sync::mpsc::Sender<T>),
+    Unbounded(tokio::sync::mpsc::UnboundedSender<T>),
+}
+
+pub enum TokioRx<T> {
+    Bounded(tokio::sync::mpsc::Receiver<T>),
+    Unbounded(tokio::sync::mpsc::UnboundedReceiver<T>),
+}
+
+impl<T> Clone for TokioTx<T> {
+    fn clone(&self) -> Self {
+        match self {
+            TokioTx::Bounded(tx) => TokioTx::Bounded(tx.clone()),
+            TokioTx::Unbounded(tx) => TokioTx::Unbounded(tx.clone()),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Channel<T> for TokioMpsc {
+    type Tx = TokioTx<T>;
+    type Rx = TokioRx<T>;
+
+    const NAME: &'static str = "tokio-mpsc";
+    const ASYNC: bool = true;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(0) => None,
+            Capacity::Bounded(n) => {
+                let (tx, rx) = tokio::sync::mpsc::channel(n);
+                Some((TokioTx::Bounded(tx), TokioRx::Bounded(rx)))
+            }
+            Capacity::Unbounded => {
+                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                Some((TokioTx::Unbounded(tx), TokioRx::Unbounded(rx)))
+            }
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, _rx: &Self::Rx) -> Option<Self::Rx> {
+        None
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for TokioTx<T> {
+    async fn send(&mut self, msg: T) -> Result<(), T> {
+        match self {
+            TokioTx::Bounded(tx) => tx.send(msg).await.map_err(|e| e.0),
+            TokioTx::Unbounded(tx) => tx.send(msg).map_err(|e| e.0),
+        }
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        block_on(Tx::send(self, msg))
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for TokioRx<T> {
+    async fn recv(&mut self) -> Option<T> {
+        match self {
+            TokioRx::Bounded(rx) => rx.recv().await,
+            TokioRx::Unbounded(rx) => rx.recv().await,
+        }
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        block_on(Rx::recv(self))
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        match self {
+            TokioRx::Bounded(rx) => rx.try_recv().ok(),
+            TokioRx::Unbounded(rx) => rx.try_recv().ok(),
+        }
+    }
+}
+
+/// Sends never wait. Receivers which fall more than the capacity behind lose
+/// the oldest messages. Runs which lose any are reported as lossy
+pub struct TokioBroadcast;
+
+/// tokio's broadcast receiver waits forever in a recv after the one which saw
+/// the channel close. This one remembers, and counts what it lagged behind on
+pub struct BroadcastRx<T> {
+    rx: tokio::sync::broadcast::Receiver<T>,
+    closed: bool,
+    lost: usize,
+}
+
+impl<T> BroadcastRx<T> {
+    fn new(rx: tokio::sync::broadcast::Receiver<T>) -> BroadcastRx<T> {
+        BroadcastRx { rx, closed: false, lost: 0 }
+    }
+}
+
+impl<T: Clone + Send + 'static> Channel<T> for TokioBroadcast {
+    type Tx = tokio::sync::broadcast::Sender<T>;
+    type Rx = BroadcastRx<T>;
+
+    const NAME: &'static str = "tokio-broadcast";
+    const ASYNC: bool = true;
+    const BROADCAST: bool = true;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(0) | Capacity::Unbounded => None,
+            Capacity::Bounded(n) => {
+                let (tx, rx) = tokio::sync::broadcast::channel(n);
+                Some((tx, BroadcastRx::new(rx)))
+            }
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(tx: &Self::Tx, _rx: &Self::Rx) -> Option<Self::Rx> {
+        Some(BroadcastRx::new(tx.subscribe()))
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for tokio::sync::broadcast::Sender<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        future::ready(self.send_blocking(msg))
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        tokio::sync::broadcast::Sender::send(self, msg).map(|_| ()).map_err(|e| e.0)
+    }
+}
+
+impl<T: Clone + Send + 'static> Rx<T> for BroadcastRx<T> {
+    async fn recv(&mut self) -> Option<T> {
+        while !self.closed {
+            match self.rx.recv().await {
+                Ok(msg) => return Some(msg),
+                Err(tokio::sync::broadcast::RecvError::Lagged(n)) => self.lost += n as usize,
+                Err(tokio::sync::broadcast::RecvError::Closed) => self.closed = true,
+            }
+        }
+
+        None
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        block_on(Rx::recv(self))
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        while !self.closed {
+            match self.rx.try_recv() {
+                Ok(msg) => return Some(msg),
+                Err(tokio::sync::broadcast::TryRecvError::Lagged(n)) => self.lost += n as usize,
+                Err(tokio::sync::broadcast::TryRecvError::Closed) => self.closed = true,
+                Err(tokio::sync::broadcast::TryRecvError::Empty) => return None,
+            }
+        }
+
+        None
+    }
+
+    fn lost(&self) -> usize {
+        self.lost
+    }
+}
+
+pub struct StdMpsc;
+
+pub enum StdTx<T> {
+    Bounded(mpsc::SyncSender<T>),
+    Unbounded(mpsc::Sender<T>),
+}
+
+impl<T> Clone for StdTx<T> {
+    fn clone(&self) -> Self {
+        match self {
+            StdTx::Bounded(tx) => StdTx::Bounded(tx.clone()),
+            StdTx::Unbounded(tx) => StdTx::Unbounded(tx.clone()),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Channel<T> for StdMpsc {
+    type Tx = StdTx<T>;
+    type Rx = mpsc::Receiver<T>;
+
+    const NAME: &'static str = "std-mpsc";
+    const ASYNC: bool = false;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(n) => {
+                let (tx, rx) = mpsc::sync_channel(n);
+                Some((StdTx::Bounded(tx), rx))
+            }
+            Capacity::Unbounded => {
+                let (tx, rx) = mpsc::channel();
+                Some((StdTx::Unbounded(tx), rx))
+            }
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, _rx: &Self::Rx) -> Option<Self::Rx> {
+        None
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for StdTx<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        future::ready(self.send_blocking(msg))
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        match self {
+            StdTx::Bounded(tx) => tx.send(msg).map_err(|e| e.0),
+            StdTx::Unbounded(tx) => tx.send(msg).map_err(|e| e.0),
+        }
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for mpsc::Receiver<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        future::ready(self.recv_blocking())
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        mpsc::Receiver::recv(self).ok()
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        mpsc::Receiver::try_recv(self).ok()
+    }
+}
+
+/// Sends to a channel without receivers don't fail
+pub struct Piper;
+
+impl<T: Clone + Send + 'static> Channel<T> for Piper {
+    type Tx = piper::Sender<T>;
+    type Rx = piper::Receiver<T>;
+
+    const NAME: &'static str = "piper";
+    const ASYNC: bool = true;
+    const SEND_FAILS_WITHOUT_RECEIVER: bool = false;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(0) | Capacity::Unbounded => None,
+            Capacity::Bounded(n) => Some(piper::chan(n)),
+        }
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, rx: &Self::Rx) -> Option<Self::Rx> {
+        Some(rx.clone())
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for piper::Sender<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        let send = piper::Sender::send(self, msg);
+        async move {
+            send.await;
+            Ok(())
+        }
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        block_on(Tx::send(self, msg))
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for piper::Receiver<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        piper::Receiver::recv(self)
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        block_on(Rx::recv(self))
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        piper::Receiver::try_recv(self)
+    }
+}
+
+/// Producers push onto a shared queue which the consumer swaps for its own
+/// drained one, taking everything queued under one lock. Single consumer and
+/// synchronous. Bounded capacity is of the shared queue
+pub struct Swap;
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Signalled when the queue gets its first message or the last sender goes
+    ready: Condvar,
+    /// Signalled when the consumer empties the queue or goes
+    space: Condvar,
+    capacity: Option<usize>,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receiver: bool,
+}
+
+pub struct SwapTx<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub struct SwapRx<T> {
+    shared: Arc<Shared<T>>,
+    /// Messages taken in the last swap
+    local: VecDeque<T>,
+}
+
+impl<T: Clone + Send + 'static> Channel<T> for Swap {
+    type Tx = SwapTx<T>;
+    type Rx = SwapRx<T>;
+
+    const NAME: &'static str = "swap";
+    const ASYNC: bool = false;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        let capacity = match capacity {
+            Capacity::Bounded(0) => return None,
+            Capacity::Bounded(n) => Some(n),
+            Capacity::Unbounded => None,
+        };
+
+        let state = State {
+            queue: VecDeque::new(),
+            senders: 1,
+            receiver: true,
+        };
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(state),
+            ready: Condvar::new(),
+            space: Condvar::new(),
+            capacity,
+        });
+
+        let rx = SwapRx {
+            shared: shared.clone(),
+            local: VecDeque::new(),
+        };
+
+        Some((SwapTx { shared }, rx))
+    }
+
+    fn sender(tx: &Self::Tx) -> Option<Self::Tx> {
+        Some(tx.clone())
+    }
+
+    fn receiver(_tx: &Self::Tx, _rx: &Self::Rx) -> Option<Self::Rx> {
+        None
+    }
+}
+
+impl<T> Clone for SwapTx<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        SwapTx {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for SwapTx<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.ready.notify_one();
+        }
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for SwapTx<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        future::ready(self.send_blocking(msg))
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        let mut state = shared.state.lock().unwrap();
+        while state.receiver && shared.capacity.is_some_and(|n| state.queue.len() >= n) {
+            state = shared.space.wait(state).unwrap();
+        }
+
+        if !state.receiver {
+            return Err(msg);
+        }
+
+        // The consumer only waits on an empty queue
+        state.queue.push_back(msg);
+        if state.queue.len() == 1 {
+            shared.ready.notify_one();
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> SwapRx<T> {
+    /// Swaps the drained local queue for the shared one. False when there
+    /// was nothing to take
+    fn swap(&mut self, wait: bool) -> bool {
+        let shared = &*self.shared;
+        let mut state = shared.state.lock().unwrap();
+        while wait && state.queue.is_empty() && state.senders > 0 {
+            state = shared.ready.wait(state).unwrap();
+        }
+
+        if state.queue.is_empty() {
+            return false;
+        }
+
+        mem::swap(&mut state.queue, &mut self.local);
+        shared.space.notify_all();
+        true
+    }
+}
+
+impl<T> Drop for SwapRx<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receiver = false;
+        self.shared.space.notify_all();
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for SwapRx<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        future::ready(self.recv_blocking())
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        if self.local.is_empty() && !self.swap(true) {
+            return None;
+        }
+
+        self.local.pop_front()
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        if self.local.is_empty() && !self.swap(false) {
+            return None;
+        }
+
+        self.local.pop_front()
+    }
+}
+
+/// The in-crate ring. Single producer and single consumer
+pub struct Ring;
+
+impl<T: Clone + Send + 'static> Channel<T> for Ring {
+    type Tx = Producer<T>;
+    type Rx = Consumer<T>;
+
+    const NAME: &'static str = "ring";
+    const ASYNC: bool = true;
+
+    fn new(capacity: Capacity) -> Option<(Self::Tx, Self::Rx)> {
+        match capacity {
+            Capacity::Bounded(0) | Capacity::Unbounded => None,
+            Capacity::Bounded(n) => Some(ring::ring(n)),
+        }
+    }
+
+    fn sender(_tx: &Self::Tx) -> Option<Self::Tx> {
+        None
+    }
+
+    fn receiver(_tx: &Self::Tx, _rx: &Self::Rx) -> Option<Self::Rx> {
+        None
+    }
+}
+
+impl<T: Send + 'static> Tx<T> for Producer<T> {
+    fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send {
+        Producer::send(self, msg)
+    }
+
+    fn send_blocking(&mut self, msg: T) -> Result<(), T> {
+        Producer::send_blocking(self, msg)
+    }
+}
+
+impl<T: Send + 'static> Rx<T> for Consumer<T> {
+    fn recv(&mut self) -> impl Future<Output = Option<T>> + Send {
+        Consumer::recv(self)
+    }
+
+    fn recv_blocking(&mut self) -> Option<T> {
+        Consumer::recv_blocking(self)
+    }
+
+    fn try_recv(&mut self) -> Option<T> {
+        Consumer::try_recv(self).ok()
+    }
+
+    fn try_recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        Consumer::try_recv_many(self, buf, max)
+    }
+}
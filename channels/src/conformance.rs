@@ -0,0 +1,246 @@
+//! Close and drop behaviour every channel under test is expected to share.
+//! Run against each channel so that they can be swapped for one another
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::FutureExt;
+
+use crate::channel::{
+    AsyncChannel, Capacity, Channel, Crossbeam, Flume, Piper, Ring, Rx, StdMpsc, Swap, TokioBroadcast, TokioMpsc, Tx,
+};
+
+const CAPACITY: Capacity = Capacity::Bounded(16);
+
+fn channel<C: Channel<T>, T: Clone + Send + 'static>(capacity: Capacity) -> (C::Tx, C::Rx) {
+    match C::new(capacity) {
+        Some(ends) => ends,
+        None => panic!("{} doesn't support {} capacity", C::NAME, capacity),
+    }
+}
+
+/// Receivers get everything sent before the last sender went, then `None`
+fn sender_drop_closes<C: Channel<u32>>() {
+    let (mut tx, mut rx) = channel::<C, u32>(CAPACITY);
+    for i in 0..3 {
+        tx.send_blocking(i).unwrap();
+    }
+
+    drop(tx);
+    for i in 0..3 {
+        assert_eq!(rx.recv_blocking(), Some(i));
+    }
+
+    assert_eq!(rx.recv_blocking(), None);
+    assert_eq!(rx.try_recv(), None);
+    assert_eq!(block_on(rx.recv()), None);
+}
+
+/// The channel stays open while any sender is left
+fn last_sender_drop_closes<C: Channel<u32>>() {
+    let (mut tx, mut rx) = channel::<C, u32>(CAPACITY);
+    let mut other = match C::sender(&tx) {
+        Some(other) => other,
+        None => return,
+    };
+
+    tx.send_blocking(1).unwrap();
+    drop(tx);
+    other.send_blocking(2).unwrap();
+    assert_eq!(rx.recv_blocking(), Some(1));
+    assert_eq!(rx.recv_blocking(), Some(2));
+
+    drop(other);
+    assert_eq!(rx.recv_blocking(), None);
+}
+
+/// Sends fail with the message once every receiver is gone, unless the
+/// channel says otherwise
+fn receiver_drop_fails_sends<C: Channel<u32>>() {
+    let (mut tx, rx) = channel::<C, u32>(CAPACITY);
+    drop(rx);
+    if C::SEND_FAILS_WITHOUT_RECEIVER {
+        assert_eq!(tx.send_blocking(1), Err(1));
+        assert_eq!(block_on(tx.send(2)), Err(2));
+    } else {
+        assert_eq!(tx.send_blocking(1), Ok(()));
+        assert_eq!(block_on(tx.send(2)), Ok(()));
+    }
+}
+
+/// A full channel whose receiver goes fails sends instead of leaving them
+/// waiting for room. Broadcast sends never wait for room and piper leaves
+/// them waiting for good
+fn receiver_drop_wakes_full_sender<C: Channel<u32>>() {
+    let (mut tx, rx) = channel::<C, u32>(Capacity::Bounded(1));
+    tx.send_blocking(1).unwrap();
+    if C::BROADCAST {
+        assert_eq!(tx.send(2).now_or_never(), Some(Ok(())));
+        return;
+    }
+
+    if !C::SEND_FAILS_WITHOUT_RECEIVER {
+        let mut send = Box::pin(tx.send(2));
+        assert!((&mut send).now_or_never().is_none());
+        drop(rx);
+        thread::sleep(Duration::from_millis(10));
+        assert!((&mut send).now_or_never().is_none());
+        return;
+    }
+
+    let sender = thread::spawn(move || tx.send_blocking(2));
+
+    // Whether the sender is waiting yet or not, it has to give up
+    thread::sleep(Duration::from_millis(10));
+    drop(rx);
+    assert_eq!(sender.join().unwrap(), Err(2));
+}
+
+/// Everything queued is received in order after the sender goes, even from
+/// a full channel
+fn drains_after_close<C: Channel<u32>>() {
+    let (mut tx, mut rx) = channel::<C, u32>(CAPACITY);
+    for i in 0..16 {
+        tx.send_blocking(i).unwrap();
+    }
+
+    drop(tx);
+    let mut batch = Vec::new();
+    assert_eq!(rx.try_recv(), Some(0));
+    assert_eq!(rx.recv_many_blocking(&mut batch, 5), 5);
+    assert_eq!(batch, vec![1, 2, 3, 4, 5]);
+    for i in 6..16 {
+        assert_eq!(block_on(rx.recv()), Some(i));
+    }
+
+    assert_eq!(rx.recv_many_blocking(&mut batch, 5), 0);
+    assert_eq!(rx.recv_blocking(), None);
+}
+
+/// Counts its drops. Also used by the ring's own tests
+#[derive(Debug, Clone)]
+pub struct Counted(pub Arc<AtomicUsize>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Messages nobody received are dropped with the channel
+fn drops_unreceived<C: Channel<Counted>>() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut tx, rx) = channel::<C, Counted>(CAPACITY);
+    for _ in 0..3 {
+        assert!(tx.send_blocking(Counted(drops.clone())).is_ok());
+    }
+
+    drop(rx);
+    drop(tx);
+    assert_eq!(drops.load(Ordering::Relaxed), 3);
+}
+
+/// A receive dropped while waiting doesn't take the next message
+fn cancelled_recv_loses_nothing<C: Channel<u32>>() {
+    assert!(C::ASYNC, "{} has no receive future to cancel", C::NAME);
+
+    let (mut tx, mut rx) = channel::<C, u32>(CAPACITY);
+    assert_eq!(rx.recv().now_or_never(), None);
+    tx.send_blocking(1).unwrap();
+    assert_eq!(rx.recv_blocking(), Some(1));
+
+    drop(tx);
+    assert_eq!(rx.recv().now_or_never(), Some(None));
+}
+
+/// A send dropped while waiting for room never delivers its message and
+/// doesn't hold on to the room it was waiting for. Broadcast sends never
+/// wait, so there's nothing to cancel
+fn cancelled_send_delivers_nothing<C: Channel<u32>>() {
+    assert!(C::ASYNC, "{} has no send future to cancel", C::NAME);
+    let (mut tx, mut rx) = channel::<C, u32>(Capacity::Bounded(1));
+    tx.send_blocking(1).unwrap();
+    if C::BROADCAST {
+        assert_eq!(tx.send(2).now_or_never(), Some(Ok(())));
+        return;
+    }
+
+    assert!(tx.send(2).now_or_never().is_none());
+    assert_eq!(rx.recv_blocking(), Some(1));
+    assert_eq!(rx.try_recv(), None);
+
+    tx.send_blocking(3).unwrap();
+    assert_eq!(rx.recv_blocking(), Some(3));
+    drop(tx);
+    assert_eq!(rx.recv_blocking(), None);
+}
+
+/// Generates the tests for a channel. Blocking channels have no futures to
+/// cancel, so their cancellation tests are ignored rather than passed
+macro_rules! conformance {
+    ($name:ident, $channel:ty, blocking) => {
+        conformance!($name, $channel, #[ignore = "blocking recv and send have no future to cancel"]);
+    };
+    ($name:ident, $channel:ty) => {
+        conformance!($name, $channel,);
+    };
+    ($name:ident, $channel:ty, $(#[$cancel:meta])*) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn sender_drop_closes() {
+                super::sender_drop_closes::<$channel>()
+            }
+
+            #[test]
+            fn last_sender_drop_closes() {
+                super::last_sender_drop_closes::<$channel>()
+            }
+
+            #[test]
+            fn receiver_drop_fails_sends() {
+                super::receiver_drop_fails_sends::<$channel>()
+            }
+
+            #[test]
+            fn receiver_drop_wakes_full_sender() {
+                super::receiver_drop_wakes_full_sender::<$channel>()
+            }
+
+            #[test]
+            fn drains_after_close() {
+                super::drains_after_close::<$channel>()
+            }
+
+            #[test]
+            fn drops_unreceived() {
+                super::drops_unreceived::<$channel>()
+            }
+
+            #[test]
+            $(#[$cancel])*
+            fn cancelled_recv_loses_nothing() {
+                super::cancelled_recv_loses_nothing::<$channel>()
+            }
+
+            #[test]
+            $(#[$cancel])*
+            fn cancelled_send_delivers_nothing() {
+                super::cancelled_send_delivers_nothing::<$channel>()
+            }
+        }
+    };
+}
+
+conformance!(crossbeam, Crossbeam, blocking);
+conformance!(flume, Flume);
+conformance!(async_channel, AsyncChannel);
+conformance!(tokio_mpsc, TokioMpsc);
+conformance!(tokio_broadcast, TokioBroadcast);
+conformance!(std_mpsc, StdMpsc, blocking);
+conformance!(piper, Piper);
+conformance!(swap, Swap, blocking);
+conformance!(ring, Ring);
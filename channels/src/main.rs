@@ -1,7 +1,363 @@
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use argh::FromArgs;
+use bytes::Bytes;
+use common::alloc;
+use packetparse::Packet;
+
+mod channel;
+#[cfg(test)]
+mod conformance;
+mod message;
+mod ring;
+mod run;
+
+use channel::{AsyncChannel, Capacity, Channel, Crossbeam, Flume, Piper, Ring, StdMpsc, Swap, TokioBroadcast, TokioMpsc};
+use message::{Message, Stamped};
+use run::{Drain, Outcome, Runtime, Spec, Stall, Topology};
+
+/// Counts live bytes so that slow consumer runs can report how much a backlog
+/// holds. Other runs stop counting up front. Benches use the system allocator
+#[cfg(not(test))]
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
+
+#[derive(FromArgs)]
+/// Reach new heights.
+struct Config {
+    /// channel to run. crossbeam, flume, async-channel, tokio-mpsc,
+    /// tokio-broadcast, std-mpsc, piper, swap or ring. repeat for more. all by
+    /// default, with tokio-broadcast only in broadcast
+    #[argh(option)]
+    channel: Vec<Kind>,
+
+    /// topology to run. spsc, mpsc, mpmc or broadcast. repeat for more. all by default
+    #[argh(option, short = 't')]
+    topology: Vec<Topology>,
+
+    /// what runs producers and consumers. threads, tokio-current,
+    /// tokio-multi or smol. repeat for more. all by default
+    #[argh(option)]
+    runtime: Vec<Runtime>,
+
+    /// producers in mpsc and mpmc
+    #[argh(option, short = 'p', default = "4", from_str_fn(common::nonzero))]
+    producers: usize,
+
+    /// consumers in mpmc and broadcast
+    #[argh(option, short = 'c', default = "4", from_str_fn(common::nonzero))]
+    consumers: usize,
+
+    /// number of messages
+    #[argh(option, short = 'n', default = "1000000")]
+    count: usize,
+
+    /// capacity of the channels. a number or unbounded. repeat for more.
+    /// 1, 16, 1000 and unbounded by default
+    #[argh(option)]
+    capacity: Vec<Capacity>,
+
+    /// message to send. u32, bytes (1 KB) or packet (a PUBLISH with a 1 KB
+    /// payload). repeat for more. all by default
+    #[argh(option)]
+    payload: Vec<Payload>,
+
+    /// how consumers receive. one (a recv per message), try-recv (a recv,
+    /// then try_recv until empty) or many:N (batches of up to N). repeat for
+    /// more. one by default
+    #[argh(option)]
+    drain: Vec<Drain>,
+
+    /// run with slow consumers which stop receiving for this many
+    /// milliseconds every `stall-every` messages. reports time producers
+    /// spend in send, time to catch up after each stall and memory held by
+    /// the backlog. repeat for more lengths
+    #[argh(option)]
+    stall: Vec<u64>,
+
+    /// messages each consumer receives between stalls
+    #[argh(option, default = "100000")]
+    stall_every: usize,
+
+    /// measure per message latency with producers offering this many
+    /// messages per second in total. repeat for more rates
+    #[argh(option, short = 'r', from_str_fn(rate))]
+    rate: Vec<f64>,
+
+    /// length of each latency run
+    #[argh(option, default = "5")]
+    seconds: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Crossbeam,
+    Flume,
+    AsyncChannel,
+    TokioMpsc,
+    TokioBroadcast,
+    StdMpsc,
+    Piper,
+    Swap,
+    Ring,
+}
+
+impl Kind {
+    const ALL: [Kind; 9] = [
+        Kind::Crossbeam,
+        Kind::Flume,
+        Kind::AsyncChannel,
+        Kind::TokioMpsc,
+        Kind::TokioBroadcast,
+        Kind::StdMpsc,
+        Kind::Piper,
+        Kind::Swap,
+        Kind::Ring,
+    ];
+
+    /// Whether to run by default. tokio-broadcast loses messages whenever a
+    /// receiver falls behind, so it only runs in broadcast unless asked for
+    fn default_for(&self, topology: Topology) -> bool {
+        match self {
+            Kind::TokioBroadcast => topology == Topology::Broadcast,
+            _ => true,
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crossbeam" => Ok(Kind::Crossbeam),
+            "flume" => Ok(Kind::Flume),
+            "async-channel" => Ok(Kind::AsyncChannel),
+            "tokio-mpsc" => Ok(Kind::TokioMpsc),
+            "tokio-broadcast" => Ok(Kind::TokioBroadcast),
+            "std-mpsc" => Ok(Kind::StdMpsc),
+            "piper" => Ok(Kind::Piper),
+            "swap" => Ok(Kind::Swap),
+            "ring" => Ok(Kind::Ring),
+            kind => Err(format!(
+                "Unknown channel {}. Expected crossbeam, flume, async-channel, tokio-mpsc, tokio-broadcast, std-mpsc, piper, swap or ring",
+                kind
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Payload {
+    U32,
+    Bytes,
+    Packet,
+}
+
+impl Payload {
+    const ALL: [Payload; 3] = [Payload::U32, Payload::Bytes, Payload::Packet];
+}
+
+impl FromStr for Payload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "u32" => Ok(Payload::U32),
+            "bytes" => Ok(Payload::Bytes),
+            "packet" => Ok(Payload::Packet),
+            payload => Err(format!("Unknown payload {}. Expected u32, bytes or packet", payload)),
+        }
+    }
+}
+
+/// Rates producers are paced at, which have to give a send interval
+fn rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) => Err("should be a number of messages per second more than 0".to_owned()),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+const CAPACITIES: [Capacity; 4] = [
+    Capacity::Bounded(1),
+    Capacity::Bounded(16),
+    Capacity::Bounded(1000),
+    Capacity::Unbounded,
+];
 
 fn main() {
-    println!("Hello, world!");
+    let config: Config = argh::from_env();
+    let kinds = if config.channel.is_empty() { Kind::ALL.to_vec() } else { config.channel.clone() };
+    let topologies = if config.topology.is_empty() { Topology::ALL.to_vec() } else { config.topology.clone() };
+    let capacities = if config.capacity.is_empty() { CAPACITIES.to_vec() } else { config.capacity.clone() };
+    let payloads = if config.payload.is_empty() { Payload::ALL.to_vec() } else { config.payload.clone() };
+    let runtimes = if config.runtime.is_empty() { Runtime::ALL.to_vec() } else { config.runtime.clone() };
+    let drains = if config.drain.is_empty() { vec![Drain::One] } else { config.drain.clone() };
+    if config.stall_every == 0 {
+        panic!("Consumers need to receive at least 1 message between stalls");
+    }
+
+    let stalls = if config.stall.is_empty() {
+        alloc::stop();
+        vec![None]
+    } else {
+        let every = config.stall_every;
+        config.stall.iter().map(|ms| Some(Stall { every, length: Duration::from_millis(*ms) })).collect()
+    };
+
+    let rates = if config.rate.is_empty() { vec![None] } else { config.rate.iter().map(|r| Some(*r)).collect() };
+
+    let mut spec = Spec {
+        producers: config.producers,
+        consumers: config.consumers,
+        count: config.count,
+        capacity: Capacity::Unbounded,
+        rate: None,
+        drain: Drain::One,
+        stall: None,
+    };
+
+    for rate in rates {
+        spec.rate = rate;
+        if let Some(rate) = rate {
+            spec.count = (rate * config.seconds as f64) as usize;
+        }
+
+        for stall in stalls.iter() {
+            spec.stall = *stall;
+            for capacity in capacities.iter() {
+                spec.capacity = *capacity;
+                for drain in drains.iter() {
+                    spec.drain = *drain;
+                    for payload in payloads.iter() {
+                        for topology in topologies.iter() {
+                            for kind in kinds.iter() {
+                                if config.channel.is_empty() && !kind.default_for(*topology) {
+                                    continue;
+                                }
+
+                                for runtime in runtimes.iter() {
+                                    bench(*kind, *runtime, *payload, *topology, &spec);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn bench(kind: Kind, runtime: Runtime, payload: Payload, topology: Topology, spec: &Spec) {
+    match payload {
+        Payload::U32 => stamped::<u32>(kind, runtime, topology, spec),
+        Payload::Bytes => stamped::<Bytes>(kind, runtime, topology, spec),
+        Payload::Packet => stamped::<Packet>(kind, runtime, topology, spec),
+    }
+}
+
+/// Messages carry their send time in latency runs
+fn stamped<M: Message>(kind: Kind, runtime: Runtime, topology: Topology, spec: &Spec) {
+    if spec.rate.is_some() {
+        channel::<Stamped<M>>(kind, runtime, topology, spec)
+    } else {
+        channel::<M>(kind, runtime, topology, spec)
+    }
+}
+
+fn channel<T: Message>(kind: Kind, runtime: Runtime, topology: Topology, spec: &Spec) {
+    match kind {
+        Kind::Crossbeam => measure::<Crossbeam, T>(runtime, topology, spec),
+        Kind::Flume => measure::<Flume, T>(runtime, topology, spec),
+        Kind::AsyncChannel => measure::<AsyncChannel, T>(runtime, topology, spec),
+        Kind::TokioMpsc => measure::<TokioMpsc, T>(runtime, topology, spec),
+        Kind::TokioBroadcast => measure::<TokioBroadcast, T>(runtime, topology, spec),
+        Kind::StdMpsc => measure::<StdMpsc, T>(runtime, topology, spec),
+        Kind::Piper => measure::<Piper, T>(runtime, topology, spec),
+        Kind::Swap => measure::<Swap, T>(runtime, topology, spec),
+        Kind::Ring => measure::<Ring, T>(runtime, topology, spec),
+    }
+}
+
+fn measure<C: Channel<T>, T: Message>(runtime: Runtime, topology: Topology, spec: &Spec) {
+    let (producers, consumers) = topology.shape(spec);
+    let outcome = run::run::<C, T>(runtime, topology, spec);
+    let mut label = format!(
+        "{} {} {}->{} on {} ({} capacity, {})",
+        C::NAME,
+        topology,
+        producers,
+        consumers,
+        runtime,
+        spec.capacity,
+        T::NAME
+    );
+
+    if spec.drain != Drain::One {
+        label = format!("{} draining {}", label, spec.drain);
+    }
+
+    if let Some(stall) = spec.stall {
+        label = format!("{} stalling {:?} every {} msgs", label, stall.length, stall.every);
+    }
+
+    if let Some(rate) = spec.rate {
+        label = format!("{} at {} msgs/s", label, rate);
+    }
+
+    match outcome {
+        Some(outcome) => report(&label, &outcome),
+        None => println!("{} unsupported", label),
+    }
+}
+
+fn report(label: &str, outcome: &Outcome) {
+    // A throughput of what got through isn't comparable with channels which
+    // deliver everything
+    if outcome.received != outcome.expected {
+        println!(
+            "{} lossy, elapsed = {:?}, received = {}/{}, lost = {}",
+            label, outcome.elapsed, outcome.received, outcome.expected, outcome.lost
+        );
+
+        return;
+    }
+
+    let throughput = outcome.received as f64 / outcome.elapsed.as_secs_f64() / 1_000_000.0;
+    println!(
+        "{} throughput = {:.2} M msgs/s, elapsed = {:?}, received = {}/{}",
+        label, throughput, outcome.elapsed, outcome.received, outcome.expected
+    );
+
+    if !outcome.latencies.is_empty() {
+        outcome.latencies.print(&format!("{} latency", label));
+    }
+
+    // Only stall runs time sends
+    let sends = &outcome.sends;
+    if sends.is_empty() {
+        return;
+    }
+
+    let blocked = Duration::from_secs_f64(sends.mean().as_secs_f64() * sends.len() as f64);
+    let growth = outcome.growth as f64 / 1024.0 / 1024.0;
+    println!(
+        "{} blocked in send = {:?}, recoveries = {}, memory growth = {:.2} MB",
+        label,
+        blocked,
+        outcome.recoveries.len(),
+        growth
+    );
+
+    sends.print(&format!("{} send", label));
+    if !outcome.recoveries.is_empty() {
+        outcome.recoveries.print(&format!("{} recovery", label));
+    }
 }
 
 #[cfg(test)]
@@ -11,45 +367,319 @@ mod test {
     use std::thread;
     use test::Bencher;
 
-    #[bench]
-    fn spsc_1000000_smol(b: &mut Bencher) {
+    use crate::channel::{Capacity, Channel, Ring, Rx, TokioMpsc, Tx};
+    use crate::message::Message;
+    use crate::run::{self, Drain, Runtime, Spec, Topology};
+
+    const COUNT: usize = 1_000_000;
+
+    /// piper producer blocked on its own thread, consumer on smol
+    fn bench_piper_on_smol<M: Message>(b: &mut Bencher, capacity: usize) {
         b.iter(move || {
-            let (tx, rx) = piper::chan(1000);
+            let (tx, rx) = piper::chan(capacity);
             thread::spawn(move || {
                 smol::block_on(async {
                     let tx = tx;
-                    for i in 0..1_000_000u32 {
-                        tx.send(i).await;
+                    for i in 0..COUNT {
+                        tx.send(M::new(i, None)).await;
                     }
                 });
             });
 
-            smol::run(async {
-                for _i in 0..1_000_000u32 {
-                    rx.recv().await;
+            let received = smol::run(async {
+                let mut received = 0;
+                while rx.recv().await.is_some() {
+                    received += 1;
                 }
+
+                received
             });
+
+            assert_eq!(received, COUNT);
         });
     }
 
-    #[bench]
-    fn spsc_1000000_tokio(b: &mut Bencher) {
+    /// The in-crate ring set up like `bench_piper_on_smol`
+    fn bench_ring_on_smol<M: Message>(b: &mut Bencher, capacity: usize) {
         b.iter(move || {
-            let (tx, mut rx) = tokio::sync::mpsc::channel(1000);
+            let (mut tx, mut rx) = crate::ring::ring(capacity);
             thread::spawn(move || {
                 smol::block_on(async {
-                    let mut tx = tx;
-                    for i in 0..1_000_000u32 {
-                        tx.send(i).await.unwrap();
+                    for i in 0..COUNT {
+                        if tx.send(M::new(i, None)).await.is_err() {
+                            panic!("Receiver dropped");
+                        }
                     }
                 });
             });
 
-            smol::run(async {
-                for _i in 0..1_000_000u32 {
-                    let _ = rx.recv().await;
+            let received = smol::run(async {
+                let mut received = 0;
+                while rx.recv().await.is_some() {
+                    received += 1;
                 }
+
+                received
             });
+
+            assert_eq!(received, COUNT);
         });
     }
+
+    /// The ring with the producer publishing `batch` messages at a time and
+    /// the consumer taking whatever is ready, on threads
+    fn bench_ring_batched<M: Message>(b: &mut Bencher, capacity: usize, batch: usize) {
+        b.iter(move || {
+            let (mut tx, mut rx) = crate::ring::ring(capacity);
+            thread::spawn(move || {
+                let mut msgs = Vec::with_capacity(batch);
+                for i in 0..COUNT {
+                    msgs.push(M::new(i, None));
+                    if msgs.len() == batch {
+                        tx.send_many_blocking(&mut msgs);
+                    }
+                }
+
+                tx.send_many_blocking(&mut msgs);
+            });
+
+            let mut msgs = Vec::with_capacity(batch);
+            let mut received = 0;
+            loop {
+                received += rx.try_recv_many(&mut msgs, batch);
+                if msgs.is_empty() {
+                    match rx.recv_blocking() {
+                        Some(_) => received += 1,
+                        None => break,
+                    }
+                }
+
+                msgs.clear();
+            }
+
+            assert_eq!(received, COUNT);
+        });
+    }
+
+    /// tokio's channel off its runtime. Wakeups go through smol
+    fn bench_tokio_on_smol<M: Message>(b: &mut Bencher, capacity: Capacity) {
+        b.iter(move || {
+            let (mut tx, mut rx) = <TokioMpsc as Channel<M>>::new(capacity).unwrap();
+            thread::spawn(move || {
+                smol::block_on(async {
+                    for i in 0..COUNT {
+                        if tx.send(M::new(i, None)).await.is_err() {
+                            panic!("Receiver dropped");
+                        }
+                    }
+                });
+            });
+
+            let received = smol::run(async {
+                let mut received = 0;
+                while rx.recv().await.is_some() {
+                    received += 1;
+                }
+
+                received
+            });
+
+            assert_eq!(received, COUNT);
+        });
+    }
+
+    /// Producer and consumer as tasks, or threads, on `runtime`
+    fn bench_on<C: Channel<M>, M: Message>(b: &mut Bencher, runtime: Runtime, capacity: Capacity, drain: Drain) {
+        let spec = Spec {
+            producers: 1,
+            consumers: 1,
+            count: COUNT,
+            capacity,
+            rate: None,
+            drain,
+            stall: None,
+        };
+
+        b.iter(|| {
+            let outcome = run::run::<C, M>(runtime, Topology::Spsc, &spec).unwrap();
+            assert_eq!(outcome.received, outcome.expected);
+        });
+    }
+
+    /// Benches for each capacity with `$payload` messages, named by channel
+    /// and runtime. piper and the ring have no unbounded channel
+    macro_rules! spsc_1000000 {
+        ($name:ident, $payload:ty) => {
+            mod $name {
+                use super::*;
+
+                #[bench]
+                fn piper_on_smol_1(b: &mut Bencher) {
+                    bench_piper_on_smol::<$payload>(b, 1)
+                }
+
+                #[bench]
+                fn piper_on_smol_16(b: &mut Bencher) {
+                    bench_piper_on_smol::<$payload>(b, 16)
+                }
+
+                #[bench]
+                fn piper_on_smol_1000(b: &mut Bencher) {
+                    bench_piper_on_smol::<$payload>(b, 1000)
+                }
+
+                #[bench]
+                fn ring_on_smol_1(b: &mut Bencher) {
+                    bench_ring_on_smol::<$payload>(b, 1)
+                }
+
+                #[bench]
+                fn ring_on_smol_16(b: &mut Bencher) {
+                    bench_ring_on_smol::<$payload>(b, 16)
+                }
+
+                #[bench]
+                fn ring_on_smol_1000(b: &mut Bencher) {
+                    bench_ring_on_smol::<$payload>(b, 1000)
+                }
+
+                #[bench]
+                fn ring_on_threads_16(b: &mut Bencher) {
+                    bench_on::<Ring, $payload>(b, Runtime::Threads, Capacity::Bounded(16), Drain::One)
+                }
+
+                #[bench]
+                fn ring_on_threads_1000(b: &mut Bencher) {
+                    bench_on::<Ring, $payload>(b, Runtime::Threads, Capacity::Bounded(1000), Drain::One)
+                }
+
+                #[bench]
+                fn ring_batched_64_on_threads_1000(b: &mut Bencher) {
+                    bench_ring_batched::<$payload>(b, 1000, 64)
+                }
+
+                #[bench]
+                fn ring_on_tokio_multi_16(b: &mut Bencher) {
+                    bench_on::<Ring, $payload>(b, Runtime::TokioMulti, Capacity::Bounded(16), Drain::One)
+                }
+
+                #[bench]
+                fn ring_on_tokio_multi_1000(b: &mut Bencher) {
+                    bench_on::<Ring, $payload>(b, Runtime::TokioMulti, Capacity::Bounded(1000), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_smol_1(b: &mut Bencher) {
+                    bench_tokio_on_smol::<$payload>(b, Capacity::Bounded(1))
+                }
+
+                #[bench]
+                fn tokio_on_smol_16(b: &mut Bencher) {
+                    bench_tokio_on_smol::<$payload>(b, Capacity::Bounded(16))
+                }
+
+                #[bench]
+                fn tokio_on_smol_1000(b: &mut Bencher) {
+                    bench_tokio_on_smol::<$payload>(b, Capacity::Bounded(1000))
+                }
+
+                #[bench]
+                fn tokio_on_smol_unbounded(b: &mut Bencher) {
+                    bench_tokio_on_smol::<$payload>(b, Capacity::Unbounded)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_current_16(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioCurrent, Capacity::Bounded(16), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_current_1000(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioCurrent, Capacity::Bounded(1000), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_current_unbounded(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioCurrent, Capacity::Unbounded, Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_multi_16(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioMulti, Capacity::Bounded(16), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_multi_1000(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioMulti, Capacity::Bounded(1000), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_tokio_multi_unbounded(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::TokioMulti, Capacity::Unbounded, Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_threads_16(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::Threads, Capacity::Bounded(16), Drain::One)
+                }
+
+                #[bench]
+                fn tokio_on_threads_unbounded(b: &mut Bencher) {
+                    bench_on::<TokioMpsc, $payload>(b, Runtime::Threads, Capacity::Unbounded, Drain::One)
+                }
+            }
+        };
+    }
+
+    spsc_1000000!(spsc_1000000_u32, u32);
+    spsc_1000000!(spsc_1000000_bytes, bytes::Bytes);
+    spsc_1000000!(spsc_1000000_packet, packetparse::Packet);
+
+    /// Per message receives against batched draining, 1000 capacity
+    mod drain_1000000_u32 {
+        use super::*;
+        use crate::channel::{Flume, Swap};
+
+        const CAPACITY: Capacity = Capacity::Bounded(1000);
+
+        #[bench]
+        fn flume_one(b: &mut Bencher) {
+            bench_on::<Flume, u32>(b, Runtime::Threads, CAPACITY, Drain::One)
+        }
+
+        #[bench]
+        fn flume_try_recv(b: &mut Bencher) {
+            bench_on::<Flume, u32>(b, Runtime::Threads, CAPACITY, Drain::TryRecv)
+        }
+
+        #[bench]
+        fn flume_many_64(b: &mut Bencher) {
+            bench_on::<Flume, u32>(b, Runtime::Threads, CAPACITY, Drain::Many(64))
+        }
+
+        #[bench]
+        fn tokio_one(b: &mut Bencher) {
+            bench_on::<TokioMpsc, u32>(b, Runtime::TokioMulti, CAPACITY, Drain::One)
+        }
+
+        #[bench]
+        fn tokio_try_recv(b: &mut Bencher) {
+            bench_on::<TokioMpsc, u32>(b, Runtime::TokioMulti, CAPACITY, Drain::TryRecv)
+        }
+
+        #[bench]
+        fn tokio_many_64(b: &mut Bencher) {
+            bench_on::<TokioMpsc, u32>(b, Runtime::TokioMulti, CAPACITY, Drain::Many(64))
+        }
+
+        #[bench]
+        fn swap_one(b: &mut Bencher) {
+            bench_on::<Swap, u32>(b, Runtime::Threads, CAPACITY, Drain::One)
+        }
+
+        #[bench]
+        fn swap_many_64(b: &mut Bencher) {
+            bench_on::<Swap, u32>(b, Runtime::Threads, CAPACITY, Drain::Many(64))
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use std::time::Instant;
+
+use bytes::Bytes;
+use packetparse::Packet;
+
+/// Size of `Bytes` payloads and of the payload of `Packet`s
+const PAYLOAD_SIZE: usize = 1024;
+
+thread_local! {
+    /// Payloads share a buffer like those split off a connection's read
+    /// buffer do. Sending one costs a reference count, not a copy
+    static PAYLOAD: Bytes = Bytes::from(vec![1u8; PAYLOAD_SIZE]);
+}
+
+/// What goes through the channels
+pub trait Message: Clone + Send + Sync + 'static {
+    const NAME: &'static str;
+
+    /// The i-th message of a run. `sent` is when it was due to be sent, for
+    /// runs at a fixed rate
+    fn new(i: usize, sent: Option<Instant>) -> Self;
+
+    /// When the message was sent, for messages which carry it
+    fn sent(&self) -> Option<Instant> {
+        None
+    }
+}
+
+impl Message for u32 {
+    const NAME: &'static str = "u32";
+
+    fn new(i: usize, _sent: Option<Instant>) -> u32 {
+        i as u32
+    }
+}
+
+impl Message for Bytes {
+    const NAME: &'static str = "bytes";
+
+    fn new(_i: usize, _sent: Option<Instant>) -> Bytes {
+        PAYLOAD.with(|payload| payload.clone())
+    }
+}
+
+/// A QoS 1 PUBLISH as the router gets it from a connection
+impl Message for Packet {
+    const NAME: &'static str = "packet";
+
+    fn new(i: usize, _sent: Option<Instant>) -> Packet {
+        Packet {
+            topic: "hello/mqtt/channels/test".to_owned(),
+            dup: false,
+            retain: false,
+            qos: 1,
+            pkid: (i % 65535 + 1) as u16,
+            payload: PAYLOAD.with(|payload| payload.clone()),
+        }
+    }
+}
+
+/// A message stamped with its send time so that receivers can tell how long
+/// it took to get to them
+#[derive(Clone)]
+pub struct Stamped<M> {
+    sent: Instant,
+    /// Only carried, so that it costs what it would unstamped
+    #[allow(dead_code)]
+    msg: M,
+}
+
+impl<M: Message> Message for Stamped<M> {
+    const NAME: &'static str = M::NAME;
+
+    fn new(i: usize, sent: Option<Instant>) -> Stamped<M> {
+        Stamped {
+            sent: sent.unwrap_or_else(Instant::now),
+            msg: M::new(i, sent),
+        }
+    }
+
+    fn sent(&self) -> Option<Instant> {
+        Some(self.sent)
+    }
+}
@@ -0,0 +1,483 @@
+//! Bounded single producer single consumer ring buffer. Each end owns one
+//! index and caches the other's, so sends and receives only touch the other
+//! end's cache line when the ring looks full or empty. Batches publish their
+//! index once for all the messages in them
+use std::cell::UnsafeCell;
+use std::future::Future;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+
+use futures::executor::block_on;
+use futures::future::poll_fn;
+use futures::task::AtomicWaker;
+
+#[derive(Debug, PartialEq)]
+pub enum TrySendError<T> {
+    Full(T),
+    /// The consumer is gone
+    Closed(T),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TryRecvError {
+    Empty,
+    /// The producer is gone and everything it sent has been received
+    Closed,
+}
+
+/// Keeps what it holds on a cache line of its own
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+struct Shared<T> {
+    /// Position of the next message to receive. Only the consumer moves it
+    head: CachePadded<AtomicUsize>,
+    /// Position after the last published message. Only the producer moves it
+    tail: CachePadded<AtomicUsize>,
+    /// Consumer waiting for messages
+    receiving: CachePadded<AtomicWaker>,
+    /// Producer waiting for room
+    sending: CachePadded<AtomicWaker>,
+    /// Set when either end goes
+    closed: AtomicBool,
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+// Slots between head and tail belong to the consumer, the rest to the
+// producer. Each end only touches its own
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn slot(&self, position: usize) -> *mut MaybeUninit<T> {
+        self.slots[position % self.slots.len()].get()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let mut head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        while head != tail {
+            unsafe { ptr::drop_in_place((*self.slot(head)).as_mut_ptr()) };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    /// Position of the next message to write
+    tail: usize,
+    /// Consumer's head when last looked at
+    head: usize,
+}
+
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    /// Position of the next message to read
+    head: usize,
+    /// Producer's tail when last looked at
+    tail: usize,
+}
+
+/// Ring with room for `capacity` messages
+pub fn ring<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "Ring needs room for at least 1 message");
+    let slots = (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect();
+    let shared = Arc::new(Shared {
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+        receiving: CachePadded(AtomicWaker::new()),
+        sending: CachePadded(AtomicWaker::new()),
+        closed: AtomicBool::new(false),
+        slots,
+    });
+
+    let producer = Producer {
+        shared: shared.clone(),
+        tail: 0,
+        head: 0,
+    };
+
+    let consumer = Consumer { shared, head: 0, tail: 0 };
+    (producer, consumer)
+}
+
+impl<T> Producer<T> {
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// Free slots. Only looks at the consumer's head when the cached one
+    /// leaves less than `wanted`
+    fn room(&mut self, wanted: usize) -> usize {
+        let capacity = self.shared.slots.len();
+        if capacity - self.tail.wrapping_sub(self.head) < wanted {
+            self.head = self.shared.head.load(Ordering::Acquire);
+        }
+
+        capacity - self.tail.wrapping_sub(self.head)
+    }
+
+    fn write(&mut self, msg: T) {
+        unsafe { (*self.shared.slot(self.tail)).as_mut_ptr().write(msg) };
+        self.tail = self.tail.wrapping_add(1);
+    }
+
+    /// Makes written messages visible to the consumer
+    fn publish(&self) {
+        self.shared.tail.store(self.tail, Ordering::Release);
+        self.shared.receiving.wake();
+    }
+
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        if self.is_closed() {
+            return Err(TrySendError::Closed(msg));
+        }
+
+        if self.room(1) == 0 {
+            return Err(TrySendError::Full(msg));
+        }
+
+        self.write(msg);
+        self.publish();
+        Ok(())
+    }
+
+    /// Waits for room. Fails with the message once the consumer is gone
+    pub fn send(&mut self, msg: T) -> impl Future<Output = Result<(), T>> + Send + '_
+    where
+        T: Send,
+    {
+        let mut msg = Some(msg);
+        poll_fn(move |cx| {
+            let result = match self.try_send(msg.take().unwrap()) {
+                Err(TrySendError::Full(m)) => {
+                    // Registered before looking again so that room made in
+                    // between wakes this task
+                    self.shared.sending.register(cx.waker());
+                    self.try_send(m)
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(m)),
+                Err(TrySendError::Full(m)) => {
+                    msg = Some(m);
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    pub fn send_blocking(&mut self, msg: T) -> Result<(), T>
+    where
+        T: Send,
+    {
+        block_on(self.send(msg))
+    }
+}
+
+/// Batches. Only the benches send them
+#[cfg_attr(not(test), allow(dead_code))]
+impl<T> Producer<T> {
+    /// Moves as many messages as there is room for from the front of `msgs`
+    /// and publishes them together. 0 when full or the consumer is gone
+    pub fn try_send_many(&mut self, msgs: &mut Vec<T>) -> usize {
+        if self.is_closed() {
+            return 0;
+        }
+
+        let n = self.room(msgs.len()).min(msgs.len());
+        for msg in msgs.drain(..n) {
+            self.write(msg);
+        }
+
+        if n > 0 {
+            self.publish();
+        }
+
+        n
+    }
+
+    /// Waits for room until all of `msgs` is sent, publishing as many at a
+    /// time as fit. Whatever is left in `msgs` wasn't sent as the consumer is
+    /// gone
+    pub fn send_many<'a>(&'a mut self, msgs: &'a mut Vec<T>) -> impl Future<Output = ()> + Send + 'a
+    where
+        T: Send,
+    {
+        poll_fn(move |cx| {
+            self.try_send_many(msgs);
+            if msgs.is_empty() || self.is_closed() {
+                return Poll::Ready(());
+            }
+
+            self.shared.sending.register(cx.waker());
+            self.try_send_many(msgs);
+            if msgs.is_empty() || self.is_closed() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+    }
+
+    pub fn send_many_blocking(&mut self, msgs: &mut Vec<T>)
+    where
+        T: Send,
+    {
+        block_on(self.send_many(msgs))
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.receiving.wake();
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Published messages not yet received. Only looks at the producer's
+    /// tail when the cached one gives less than `wanted`
+    fn available(&mut self, wanted: usize) -> usize {
+        if self.tail.wrapping_sub(self.head) < wanted {
+            self.tail = self.shared.tail.load(Ordering::Acquire);
+        }
+
+        self.tail.wrapping_sub(self.head)
+    }
+
+    fn read(&mut self) -> T {
+        let msg = unsafe { (*self.shared.slot(self.head)).as_ptr().read() };
+        self.head = self.head.wrapping_add(1);
+        msg
+    }
+
+    /// Hands read slots back to the producer
+    fn release(&self) {
+        self.shared.head.store(self.head, Ordering::Release);
+        self.shared.sending.wake();
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if self.available(1) == 0 {
+            if !self.shared.closed.load(Ordering::Acquire) {
+                return Err(TryRecvError::Empty);
+            }
+
+            // The producer publishes before it closes
+            if self.available(1) == 0 {
+                return Err(TryRecvError::Closed);
+            }
+        }
+
+        let msg = self.read();
+        self.release();
+        Ok(msg)
+    }
+
+    /// Appends up to `max` ready messages to `buf` and hands their slots back
+    /// together. 0 when empty or closed
+    pub fn try_recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        let n = self.available(max).min(max);
+        for _ in 0..n {
+            let msg = self.read();
+            buf.push(msg);
+        }
+
+        if n > 0 {
+            self.release();
+        }
+
+        n
+    }
+
+    /// Waits for a message. `None` once the producer is gone and everything
+    /// it sent has been received
+    pub fn recv(&mut self) -> impl Future<Output = Option<T>> + Send + '_
+    where
+        T: Send,
+    {
+        poll_fn(move |cx| {
+            let result = match self.try_recv() {
+                Err(TryRecvError::Empty) => {
+                    // Registered before looking again so that messages
+                    // published in between wake this task
+                    self.shared.receiving.register(cx.waker());
+                    self.try_recv()
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(msg) => Poll::Ready(Some(msg)),
+                Err(TryRecvError::Closed) => Poll::Ready(None),
+                Err(TryRecvError::Empty) => Poll::Pending,
+            }
+        })
+    }
+
+    pub fn recv_blocking(&mut self) -> Option<T>
+    where
+        T: Send,
+    {
+        block_on(self.recv())
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.sending.wake();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::conformance::Counted;
+
+    const COUNT: usize = 1_000_000;
+    const CAPACITIES: [usize; 4] = [1, 2, 7, 1024];
+
+    #[test]
+    fn blocking_keeps_order() {
+        for capacity in CAPACITIES.iter() {
+            let (mut tx, mut rx) = ring(*capacity);
+            let producer = thread::spawn(move || {
+                for i in 0..COUNT {
+                    tx.send_blocking(i).unwrap();
+                }
+            });
+
+            for i in 0..COUNT {
+                assert_eq!(rx.recv_blocking(), Some(i));
+            }
+
+            assert_eq!(rx.recv_blocking(), None);
+            producer.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn batches_keep_order() {
+        for capacity in CAPACITIES.iter() {
+            let (mut tx, mut rx) = ring(*capacity);
+            let producer = thread::spawn(move || {
+                let mut batch = Vec::new();
+                for i in 0..COUNT {
+                    batch.push(i);
+                    if batch.len() == 100 {
+                        tx.send_many_blocking(&mut batch);
+                        assert!(batch.is_empty());
+                    }
+                }
+
+                tx.send_many_blocking(&mut batch);
+            });
+
+            let mut batch = Vec::new();
+            let mut next = 0;
+            while next < COUNT {
+                if rx.try_recv_many(&mut batch, 64) == 0 {
+                    batch.push(rx.recv_blocking().unwrap());
+                }
+
+                for i in batch.drain(..) {
+                    assert_eq!(i, next);
+                    next += 1;
+                }
+            }
+
+            assert_eq!(rx.recv_blocking(), None);
+            producer.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn async_keeps_order() {
+        for capacity in CAPACITIES.iter() {
+            let (mut tx, mut rx) = ring(*capacity);
+            let producer = thread::spawn(move || {
+                smol::block_on(async {
+                    for i in 0..COUNT {
+                        tx.send(i).await.unwrap();
+                    }
+                })
+            });
+
+            smol::run(async {
+                for i in 0..COUNT {
+                    assert_eq!(rx.recv().await, Some(i));
+                }
+
+                assert_eq!(rx.recv().await, None);
+            });
+
+            producer.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn close() {
+        let (mut tx, mut rx) = ring(2);
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+        // Published messages outlive the producer
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+
+        let (mut tx, rx) = ring(2);
+        drop(rx);
+        assert_eq!(tx.try_send(1), Err(TrySendError::Closed(1)));
+        assert_eq!(tx.send_blocking(2), Err(2));
+    }
+
+    #[test]
+    fn drops_unreceived() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut tx, mut rx) = ring(4);
+
+        // Wraps around the ring a few times
+        for _ in 0..10 {
+            tx.try_send(Counted(drops.clone())).unwrap();
+            tx.try_send(Counted(drops.clone())).unwrap();
+            drop(rx.try_recv().unwrap());
+            drop(rx.try_recv().unwrap());
+        }
+
+        for _ in 0..3 {
+            tx.try_send(Counted(drops.clone())).unwrap();
+        }
+
+        assert_eq!(drops.load(Ordering::Relaxed), 20);
+        drop(tx);
+        drop(rx);
+        assert_eq!(drops.load(Ordering::Relaxed), 23);
+    }
+}
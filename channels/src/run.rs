@@ -0,0 +1,709 @@
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::alloc;
+use common::latency::Latencies;
+
+use crate::channel::{Capacity, Channel, Rx, Tx};
+use crate::message::Message;
+
+/// Producers only sleep when they are at least this far ahead of schedule.
+/// Timers can't do better and the messages are stamped with when they were
+/// due anyway
+const SLACK: Duration = Duration::from_millis(1);
+
+/// Who sends to whom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// 1 → 1
+    Spsc,
+    /// N → 1
+    Mpsc,
+    /// N → M, each message to one consumer
+    Mpmc,
+    /// 1 → M, each message to every consumer. Channels which aren't broadcast
+    /// channels get a channel per consumer which the producer sends to in turn
+    Broadcast,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 4] = [Topology::Spsc, Topology::Mpsc, Topology::Mpmc, Topology::Broadcast];
+
+    /// Producers and consumers
+    pub fn shape(&self, spec: &Spec) -> (usize, usize) {
+        match self {
+            Topology::Spsc => (1, 1),
+            Topology::Mpsc => (spec.producers, 1),
+            Topology::Mpmc => (spec.producers, spec.consumers),
+            Topology::Broadcast => (1, spec.consumers),
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spsc" => Ok(Topology::Spsc),
+            "mpsc" => Ok(Topology::Mpsc),
+            "mpmc" => Ok(Topology::Mpmc),
+            "broadcast" => Ok(Topology::Broadcast),
+            t => Err(format!("Unknown topology {}. Expected spsc, mpsc, mpmc or broadcast", t)),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Topology::Spsc => "spsc",
+            Topology::Mpsc => "mpsc",
+            Topology::Mpmc => "mpmc",
+            Topology::Broadcast => "broadcast",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// What runs the producers and consumers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Runtime {
+    /// A thread each, blocking in send and recv
+    Threads,
+    TokioCurrent,
+    TokioMulti,
+    Smol,
+}
+
+impl Runtime {
+    pub const ALL: [Runtime; 4] = [Runtime::Threads, Runtime::TokioCurrent, Runtime::TokioMulti, Runtime::Smol];
+}
+
+impl FromStr for Runtime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threads" => Ok(Runtime::Threads),
+            "tokio-current" => Ok(Runtime::TokioCurrent),
+            "tokio-multi" => Ok(Runtime::TokioMulti),
+            "smol" => Ok(Runtime::Smol),
+            r => Err(format!("Unknown runtime {}. Expected threads, tokio-current, tokio-multi or smol", r)),
+        }
+    }
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Runtime::Threads => "threads",
+            Runtime::TokioCurrent => "tokio-current",
+            Runtime::TokioMulti => "tokio-multi",
+            Runtime::Smol => "smol",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// How consumers take messages off their channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Drain {
+    /// A recv per message
+    One,
+    /// A recv, then try_recv until the channel is empty
+    TryRecv,
+    /// Batches of up to N collected with recv_many
+    Many(usize),
+}
+
+impl FromStr for Drain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "one" => Ok(Drain::One),
+            None if s == "try-recv" => Ok(Drain::TryRecv),
+            Some(("many", n)) => match n.parse() {
+                Ok(0) => Err("Batches need at least 1 message".to_owned()),
+                Ok(n) => Ok(Drain::Many(n)),
+                Err(e) => Err(format!("Invalid batch size {}. {}", n, e)),
+            },
+            _ => Err(format!("Unknown drain {}. Expected one, try-recv or many:N", s)),
+        }
+    }
+}
+
+impl fmt::Display for Drain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drain::One => f.write_str("one"),
+            Drain::TryRecv => f.write_str("try-recv"),
+            Drain::Many(n) => write!(f, "many:{}", n),
+        }
+    }
+}
+
+/// Consumers stop receiving for `length` after every `every` messages
+#[derive(Debug, Clone, Copy)]
+pub struct Stall {
+    pub every: usize,
+    pub length: Duration,
+}
+
+/// What to run
+pub struct Spec {
+    pub producers: usize,
+    pub consumers: usize,
+    /// Messages sent in total, split between the producers
+    pub count: usize,
+    pub capacity: Capacity,
+    /// Messages per second offered by all producers together. As fast as
+    /// possible when `None`
+    pub rate: Option<f64>,
+    pub drain: Drain,
+    /// Slow consumer runs. Consumers drain with try_recv between stalls
+    pub stall: Option<Stall>,
+}
+
+pub struct Outcome {
+    pub elapsed: Duration,
+    /// Messages received by all consumers together
+    pub received: usize,
+    /// What `received` should be if nothing was lost
+    pub expected: usize,
+    /// Messages consumers were told they skipped
+    pub lost: usize,
+    /// Send to receive latency of stamped messages
+    pub latencies: Latencies,
+    /// Time producers spent in each send. Only in stall runs
+    pub sends: Latencies,
+    /// Time consumers took to empty their channel after each stall
+    pub recoveries: Latencies,
+    /// Most memory held at the end of a stall over what was live before the
+    /// channels were made
+    pub growth: usize,
+}
+
+impl Outcome {
+    fn new(elapsed: Duration, expected: usize, live: usize, received: Received, sends: Latencies) -> Outcome {
+        Outcome {
+            elapsed,
+            received: received.count,
+            expected,
+            lost: received.lost,
+            latencies: received.latencies,
+            sends,
+            recoveries: received.recoveries,
+            growth: received.peak.saturating_sub(live),
+        }
+    }
+}
+
+/// Senders of each producer and the consumers' receivers. A producer holds
+/// more than one sender when broadcasting over plain channels
+struct Ends<S, R> {
+    producers: Vec<Vec<S>>,
+    consumers: Vec<R>,
+    expected: usize,
+    /// Bytes live before the channels were made
+    live: usize,
+}
+
+/// `None` when the channel can't do the topology or capacity
+fn ends<C: Channel<T>, T: Message>(topology: Topology, spec: &Spec) -> Option<Ends<C::Tx, C::Rx>> {
+    let live = alloc::stats().live_bytes;
+    let (producers, consumers) = topology.shape(spec);
+    if topology == Topology::Broadcast && !C::BROADCAST {
+        let mut senders = Vec::new();
+        let mut receivers = Vec::new();
+        for _ in 0..consumers {
+            let (tx, rx) = C::new(spec.capacity)?;
+            senders.push(tx);
+            receivers.push(rx);
+        }
+
+        return Some(Ends {
+            producers: vec![senders],
+            consumers: receivers,
+            expected: spec.count * consumers,
+            live,
+        });
+    }
+
+    // Receivers of a broadcast channel don't share messages
+    if topology != Topology::Broadcast && C::BROADCAST && consumers > 1 {
+        return None;
+    }
+
+    let (tx, rx) = C::new(spec.capacity)?;
+    let mut receivers = Vec::new();
+    for _ in 1..consumers {
+        receivers.push(C::receiver(&tx, &rx)?);
+    }
+
+    let mut senders = Vec::new();
+    for _ in 1..producers {
+        senders.push(vec![C::sender(&tx)?]);
+    }
+
+    receivers.push(rx);
+    senders.push(vec![tx]);
+    let expected = if C::BROADCAST { spec.count * consumers } else { spec.count };
+    Some(Ends {
+        producers: senders,
+        consumers: receivers,
+        expected,
+        live,
+    })
+}
+
+/// Messages producer `i` of `producers` sends
+fn share(count: usize, producers: usize, i: usize) -> usize {
+    count / producers + if i < count % producers { 1 } else { 0 }
+}
+
+/// Schedule of a producer offering a fixed rate. Messages are due at fixed
+/// intervals from the start, whether or not earlier sends were held up
+struct Pace {
+    start: Instant,
+    interval: Duration,
+    sent: u32,
+    due: Instant,
+}
+
+impl Pace {
+    fn new(spec: &Spec, producers: usize) -> Option<Pace> {
+        let rate = spec.rate? / producers as f64;
+        let start = Instant::now();
+        Some(Pace {
+            start,
+            interval: Duration::from_secs_f64(1.0 / rate),
+            sent: 0,
+            due: start,
+        })
+    }
+
+    /// Moves on to the next message. Returns how long to sleep before sending it
+    fn wait(&mut self) -> Option<Duration> {
+        self.due = self.start + self.interval * self.sent;
+        self.sent += 1;
+        self.due.checked_duration_since(Instant::now()).filter(|wait| *wait >= SLACK)
+    }
+
+    /// Send time of the current message. When it was due, or now if it is
+    /// going out early
+    fn stamp(&self) -> Instant {
+        self.due.min(Instant::now())
+    }
+}
+
+/// What a consumer got
+struct Received {
+    count: usize,
+    lost: usize,
+    latencies: Latencies,
+    recoveries: Latencies,
+    /// Bytes live at the end of the worst stall
+    peak: usize,
+}
+
+impl Received {
+    fn new() -> Received {
+        Received {
+            count: 0,
+            lost: 0,
+            latencies: Latencies::new(),
+            recoveries: Latencies::new(),
+            peak: 0,
+        }
+    }
+
+    fn record<T: Message>(&mut self, msg: &T) {
+        self.count += 1;
+        if let Some(sent) = msg.sent() {
+            self.latencies.record(sent.elapsed());
+        }
+    }
+
+    /// Whether it's time to stall
+    fn stalls(&self, stall: Stall) -> bool {
+        self.count.is_multiple_of(stall.every)
+    }
+
+    /// Notes what the backlog holds at the end of a stall. Returns when
+    /// receiving resumed
+    fn resume(&mut self) -> Instant {
+        self.peak = self.peak.max(alloc::stats().live_bytes);
+        Instant::now()
+    }
+
+    /// Records how long the consumer took to empty the channel since it
+    /// resumed, if it is catching up on a stall
+    fn caught_up(&mut self, resumed: &mut Option<Instant>) {
+        if let Some(resumed) = resumed.take() {
+            self.recoveries.record(resumed.elapsed());
+        }
+    }
+
+    fn add(&mut self, other: &Received) {
+        self.count += other.count;
+        self.lost += other.lost;
+        self.latencies.add(&other.latencies);
+        self.recoveries.add(&other.recoveries);
+        self.peak = self.peak.max(other.peak);
+    }
+}
+
+/// Time a send took, if sends are being timed
+fn record_send(sends: &mut Latencies, start: Option<Instant>) {
+    if let Some(start) = start {
+        sends.record(start.elapsed());
+    }
+}
+
+/// Receives until the channel is closed and drained
+fn consume_blocking<R: Rx<T>, T: Message>(mut rx: R, drain: Drain, stall: Option<Stall>) -> Received {
+    if let Some(stall) = stall {
+        return stalled_blocking(rx, stall);
+    }
+
+    let mut received = Received::new();
+    match drain {
+        Drain::One => {
+            while let Some(msg) = rx.recv_blocking() {
+                received.record(&msg);
+            }
+        }
+        Drain::TryRecv => {
+            while let Some(msg) = rx.recv_blocking() {
+                received.record(&msg);
+                while let Some(msg) = rx.try_recv() {
+                    received.record(&msg);
+                }
+            }
+        }
+        Drain::Many(max) => {
+            let mut batch = Vec::with_capacity(max);
+            while rx.recv_many_blocking(&mut batch, max) > 0 {
+                for msg in batch.drain(..) {
+                    received.record(&msg);
+                }
+            }
+        }
+    }
+
+    received.lost = rx.lost();
+    received
+}
+
+/// Receives with try_recv, falling back to recv once the channel is empty,
+/// and stops for the stall's length after every `every` messages. Recovery
+/// is from the end of a stall until the channel is next found empty
+fn stalled_blocking<R: Rx<T>, T: Message>(mut rx: R, stall: Stall) -> Received {
+    let mut received = Received::new();
+    let mut resumed = None;
+    loop {
+        let msg = match rx.try_recv() {
+            Some(msg) => msg,
+            None => {
+                received.caught_up(&mut resumed);
+                match rx.recv_blocking() {
+                    Some(msg) => msg,
+                    None => break,
+                }
+            }
+        };
+
+        received.record(&msg);
+        if received.stalls(stall) {
+            thread::sleep(stall.length);
+            resumed = Some(received.resume());
+        }
+    }
+
+    received.lost = rx.lost();
+    received
+}
+
+async fn consume<E: Executor, R: Rx<T>, T: Message>(mut rx: R, drain: Drain, stall: Option<Stall>) -> Received {
+    if let Some(stall) = stall {
+        return stalled::<E, R, T>(rx, stall).await;
+    }
+
+    let mut received = Received::new();
+    match drain {
+        Drain::One => {
+            while let Some(msg) = rx.recv().await {
+                received.record(&msg);
+            }
+        }
+        Drain::TryRecv => {
+            while let Some(msg) = rx.recv().await {
+                received.record(&msg);
+                while let Some(msg) = rx.try_recv() {
+                    received.record(&msg);
+                }
+            }
+        }
+        Drain::Many(max) => {
+            let mut batch = Vec::with_capacity(max);
+            while rx.recv_many(&mut batch, max).await > 0 {
+                for msg in batch.drain(..) {
+                    received.record(&msg);
+                }
+            }
+        }
+    }
+
+    received.lost = rx.lost();
+    received
+}
+
+async fn stalled<E: Executor, R: Rx<T>, T: Message>(mut rx: R, stall: Stall) -> Received {
+    let mut received = Received::new();
+    let mut resumed = None;
+    loop {
+        let msg = match rx.try_recv() {
+            Some(msg) => msg,
+            None => {
+                received.caught_up(&mut resumed);
+                match rx.recv().await {
+                    Some(msg) => msg,
+                    None => break,
+                }
+            }
+        };
+
+        received.record(&msg);
+        if received.stalls(stall) {
+            E::sleep(stall.length).await;
+            resumed = Some(received.resume());
+        }
+    }
+
+    received.lost = rx.lost();
+    received
+}
+
+fn send_blocking<S: Tx<T>, T: Message>(senders: &mut [S], msg: T) {
+    let (last, rest) = senders.split_last_mut().unwrap();
+    for tx in rest.iter_mut() {
+        if tx.send_blocking(msg.clone()).is_err() {
+            panic!("Receiver dropped");
+        }
+    }
+
+    if last.send_blocking(msg).is_err() {
+        panic!("Receiver dropped");
+    }
+}
+
+async fn send<S: Tx<T>, T: Message>(senders: &mut [S], msg: T) {
+    let (last, rest) = senders.split_last_mut().unwrap();
+    for tx in rest.iter_mut() {
+        if tx.send(msg.clone()).await.is_err() {
+            panic!("Receiver dropped");
+        }
+    }
+
+    if last.send(msg).await.is_err() {
+        panic!("Receiver dropped");
+    }
+}
+
+/// `None` when the channel can't run on the runtime, or can't do the topology
+/// or capacity. Synchronous channels only run on threads
+pub fn run<C: Channel<T>, T: Message>(runtime: Runtime, topology: Topology, spec: &Spec) -> Option<Outcome> {
+    match runtime {
+        Runtime::Threads => threads::<C, T>(topology, spec),
+        _ if !C::ASYNC => None,
+        Runtime::TokioCurrent => tokio_current::<C, T>(topology, spec),
+        Runtime::TokioMulti => tokio_multi::<C, T>(topology, spec),
+        Runtime::Smol => smol::<C, T>(topology, spec),
+    }
+}
+
+/// Every producer and consumer on its own thread, blocking in send and recv
+pub fn threads<C: Channel<T>, T: Message>(topology: Topology, spec: &Spec) -> Option<Outcome> {
+    let Ends { producers: senders, consumers, expected, live } = ends::<C, T>(topology, spec)?;
+    let producers = senders.len();
+
+    let start = Instant::now();
+    let (received, sends) = thread::scope(|s| {
+        let producers: Vec<_> = senders
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut senders)| {
+                let n = share(spec.count, producers, i);
+                let mut pace = Pace::new(spec, producers);
+                s.spawn(move || {
+                    let mut sends = Latencies::new();
+                    for j in 0..n {
+                        let sent = pace.as_mut().map(|pace| {
+                            if let Some(wait) = pace.wait() {
+                                thread::sleep(wait);
+                            }
+
+                            pace.stamp()
+                        });
+
+                        let start = spec.stall.map(|_| Instant::now());
+                        send_blocking(&mut senders, T::new(j, sent));
+                        record_send(&mut sends, start);
+                    }
+
+                    sends
+                })
+            })
+            .collect();
+
+        let consumers: Vec<_> = consumers
+            .into_iter()
+            .map(|rx| s.spawn(move || consume_blocking(rx, spec.drain, spec.stall)))
+            .collect();
+
+        let mut received = Received::new();
+        for consumer in consumers {
+            received.add(&consumer.join().unwrap());
+        }
+
+        let mut sends = Latencies::new();
+        for producer in producers {
+            sends.add(&producer.join().unwrap());
+        }
+
+        (received, sends)
+    });
+
+    Some(Outcome::new(start.elapsed(), expected, live, received, sends))
+}
+
+/// Spawns tasks and sleeps on an async runtime
+trait Executor: 'static {
+    fn spawn<F>(future: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static;
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+struct Tokio;
+
+impl Executor for Tokio {
+    fn spawn<F>(future: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = tokio::spawn(future);
+        async move { handle.await.unwrap() }
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::delay_for(duration)
+    }
+}
+
+struct Smol;
+
+impl Executor for Smol {
+    fn spawn<F>(future: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        smol::Task::spawn(future)
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        let timer = smol::Timer::after(duration);
+        async move {
+            timer.await;
+        }
+    }
+}
+
+/// Every producer and consumer as a task on the executor
+async fn tasks<E: Executor, C: Channel<T>, T: Message>(ends: Ends<C::Tx, C::Rx>, spec: &Spec) -> Outcome {
+    let Ends { producers: senders, consumers, expected, live } = ends;
+    let producers = senders.len();
+    let stall = spec.stall;
+    let start = Instant::now();
+    let producers: Vec<_> = senders
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut senders)| {
+            let n = share(spec.count, producers, i);
+            let mut pace = Pace::new(spec, producers);
+            E::spawn(async move {
+                let mut sends = Latencies::new();
+                for j in 0..n {
+                    let sent = match pace.as_mut() {
+                        Some(pace) => {
+                            if let Some(wait) = pace.wait() {
+                                E::sleep(wait).await;
+                            }
+
+                            Some(pace.stamp())
+                        }
+                        None => None,
+                    };
+
+                    let start = stall.map(|_| Instant::now());
+                    send(&mut senders, T::new(j, sent)).await;
+                    record_send(&mut sends, start);
+                }
+
+                sends
+            })
+        })
+        .collect();
+
+    let consumers: Vec<_> = consumers
+        .into_iter()
+        .map(|rx| E::spawn(consume::<E, _, T>(rx, spec.drain, stall)))
+        .collect();
+
+    let mut received = Received::new();
+    for consumer in consumers {
+        received.add(&consumer.await);
+    }
+
+    let elapsed = start.elapsed();
+    let mut sends = Latencies::new();
+    for producer in producers {
+        sends.add(&producer.await);
+    }
+
+    Outcome::new(elapsed, expected, live, received, sends)
+}
+
+/// Tasks on tokio's single threaded runtime
+pub fn tokio_current<C: Channel<T>, T: Message>(topology: Topology, spec: &Spec) -> Option<Outcome> {
+    let runtime = tokio::runtime::Builder::new().basic_scheduler().enable_all().build().unwrap();
+    tokio::<C, T>(runtime, topology, spec)
+}
+
+/// Tasks on tokio's work stealing runtime with a thread per core
+pub fn tokio_multi<C: Channel<T>, T: Message>(topology: Topology, spec: &Spec) -> Option<Outcome> {
+    let runtime = tokio::runtime::Builder::new().threaded_scheduler().enable_all().build().unwrap();
+    tokio::<C, T>(runtime, topology, spec)
+}
+
+fn tokio<C: Channel<T>, T: Message>(mut runtime: tokio::runtime::Runtime, topology: Topology, spec: &Spec) -> Option<Outcome> {
+    let ends = ends::<C, T>(topology, spec)?;
+    Some(runtime.block_on(tasks::<Tokio, C, T>(ends, spec)))
+}
+
+/// Tasks on smol, run by the calling thread
+pub fn smol<C: Channel<T>, T: Message>(topology: Topology, spec: &Spec) -> Option<Outcome> {
+    let ends = ends::<C, T>(topology, spec)?;
+    Some(smol::run(tasks::<Smol, C, T>(ends, spec)))
+}
@@ -0,0 +1,88 @@
+use std::mem;
+
+use collections::dataset::Dataset;
+use collections::*;
+use common::workload::Pattern;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+const LOOKUPS: usize = 100_000;
+
+/// Uniform as baseline, a few very hot topics, a hot 1% of topics taking 90% of
+/// the traffic and in order scans
+const PATTERNS: [Pattern; 4] = [
+    Pattern::Uniform,
+    Pattern::Zipfian(0.99),
+    Pattern::Hotspot { keys: 0.01, ops: 0.9 },
+    Pattern::Sequential,
+];
+
+/// Full scans. Throughput is the memory walked, i.e. the size of the stored
+/// entries and not just the `u64` values being summed
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate");
+    for size in SIZES.iter() {
+        let dataset = Dataset::new(*size, 0);
+
+        let v = dataset.vec();
+        group.throughput(Throughput::Bytes((v.len() * mem::size_of::<Option<u64>>()) as u64));
+        group.bench_with_input(BenchmarkId::new("vec", size), &v, |b, v| {
+            b.iter(|| iterate_vec(v))
+        });
+
+        let map = dataset.map();
+        group.throughput(Throughput::Bytes((map.len() * mem::size_of::<(String, u64)>()) as u64));
+        group.bench_with_input(BenchmarkId::new("map", size), &map, |b, map| {
+            b.iter(|| iterate_map(map))
+        });
+
+        let map = dataset.indexmap();
+        group.throughput(Throughput::Bytes((map.len() * mem::size_of::<(String, u64)>()) as u64));
+        group.bench_with_input(BenchmarkId::new("indexmap", size), &map, |b, map| {
+            b.iter(|| iterate_indexmap(map))
+        });
+    }
+
+    group.finish();
+}
+
+/// Lookups under each access pattern. Every structure is queried with the same
+/// `LOOKUPS` keys. There are enough of them that, for the larger sizes, uniform
+/// lookups don't fit in cache while skewed ones mostly do
+fn access(c: &mut Criterion) {
+    for size in SIZES.iter() {
+        let mut dataset = Dataset::new(*size, 0);
+        let v = dataset.vec();
+        let map = dataset.map();
+        let indexmap = dataset.indexmap();
+        let seahash_map = dataset.seahash_map();
+
+        for pattern in PATTERNS.iter() {
+            dataset.set_lookups(*pattern, LOOKUPS);
+            let keys = dataset.keys();
+
+            let mut group = c.benchmark_group(format!("access/{}", pattern));
+            group.throughput(Throughput::Elements(LOOKUPS as u64));
+            group.bench_with_input(BenchmarkId::new("vec", size), &v, |b, v| {
+                b.iter(|| access_vec(v, &dataset.lookups))
+            });
+
+            group.bench_with_input(BenchmarkId::new("map", size), &map, |b, map| {
+                b.iter(|| keys.iter().map(|k| access_map(map, k)).sum::<u64>())
+            });
+
+            group.bench_with_input(BenchmarkId::new("indexmap", size), &indexmap, |b, map| {
+                b.iter(|| keys.iter().map(|k| access_indexmap(map, k)).sum::<u64>())
+            });
+
+            group.bench_with_input(BenchmarkId::new("seahash_map", size), &seahash_map, |b, map| {
+                b.iter(|| keys.iter().map(|k| access_seahash_map(map, k)).sum::<u64>())
+            });
+
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, iterate, access);
+criterion_main!(benches);
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Barrier, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use collections::concurrent::{ConcurrentMap, Sharded};
+use collections::dataset::{Dataset, SEED};
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use dashmap::DashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Same keyspace as the single threaded `access` benches
+const TOPICS: usize = 1_000_000;
+/// Operations done by each thread per iteration
+const OPS: usize = 10_000;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+const SHARDS: usize = 64;
+
+/// Read/write mixes as (name, percentage of reads)
+const MIXES: [(&str, u32); 2] = [("read_heavy", 95), ("write_heavy", 20)];
+
+enum Op {
+    Read(usize),
+    Write(usize),
+}
+
+/// Per thread operations. Generated up front so that the rng isn't timed
+fn workload(threads: usize, reads: u32) -> Arc<Vec<Vec<Op>>> {
+    let workload = (0..threads)
+        .map(|t| {
+            let mut rng = StdRng::seed_from_u64(SEED + t as u64);
+            (0..OPS)
+                .map(|_| {
+                    let i = rng.gen_range(0, TOPICS);
+                    if rng.gen_range(0, 100) < reads {
+                        Op::Read(i)
+                    } else {
+                        Op::Write(i)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Arc::new(workload)
+}
+
+/// Runs `iters` rounds of the workload on every thread and returns the time
+/// between releasing the threads and the last one finishing
+fn run<M: ConcurrentMap + 'static>(map: &Arc<M>, dataset: &Arc<Dataset>, workload: &Arc<Vec<Vec<Op>>>, iters: u64) -> Duration {
+    let threads = workload.len();
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let map = map.clone();
+            let dataset = dataset.clone();
+            let workload = workload.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..iters {
+                    for op in workload[t].iter() {
+                        match op {
+                            Op::Read(i) => {
+                                black_box(map.get(&dataset.topics[*i]));
+                            }
+                            Op::Write(i) => map.update(&dataset.topics[*i], *i as u64),
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    start.elapsed()
+}
+
+fn bench_map<M: ConcurrentMap + 'static>(group: &mut BenchmarkGroup<WallTime>, name: &str, map: M, dataset: &Arc<Dataset>, reads: u32) {
+    dataset.populate(&map);
+    let map = Arc::new(map);
+    for threads in THREADS.iter() {
+        let workload = workload(*threads, reads);
+        group.throughput(Throughput::Elements((OPS * threads) as u64));
+        group.bench_function(BenchmarkId::new(name, threads), |b| {
+            b.iter_custom(|iters| run(&map, dataset, &workload, iters))
+        });
+    }
+}
+
+fn concurrent(c: &mut Criterion) {
+    let dataset = Arc::new(Dataset::new(TOPICS, 0));
+    for (mix, reads) in MIXES.iter() {
+        let mut group = c.benchmark_group(format!("concurrent/{}", mix));
+        bench_map(&mut group, "rwlock", RwLock::new(HashMap::with_capacity(TOPICS)), &dataset, *reads);
+        bench_map(&mut group, "sharded", Sharded::new(SHARDS), &dataset, *reads);
+        bench_map(&mut group, "dashmap", DashMap::with_capacity(TOPICS), &dataset, *reads);
+        bench_map(&mut group, "flurry", flurry::HashMap::with_capacity(TOPICS), &dataset, *reads);
+        group.finish();
+    }
+}
+
+criterion_group!(benches, concurrent);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+use bytes::Bytes;
+use collections::dataset::device_topics;
+use collections::retained::{BTreeStore, RetainStore, SortedVecStore, TrieStore};
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+
+/// Devices with `SENSORS` topics each
+const DEVICES: [usize; 3] = [100, 10_000, 100_000];
+const SENSORS: usize = 10;
+const PAYLOAD_SIZE: usize = 64;
+
+fn store<S: RetainStore>(mut store: S, topics: &[String], payload: &Bytes) -> S {
+    for topic in topics.iter() {
+        store.retain(topic, payload.clone());
+    }
+
+    store
+}
+
+fn bench_store<S: RetainStore>(group: &mut BenchmarkGroup<WallTime>, name: &str, devices: usize, s: &S, filter: &str) {
+    group.bench_with_input(BenchmarkId::new(name, devices), s, |b, s| b.iter(|| s.matches(filter).len()));
+}
+
+/// Retaining every topic into an empty store
+fn build(c: &mut Criterion) {
+    let payload = Bytes::from(vec![1u8; PAYLOAD_SIZE]);
+    let mut group = c.benchmark_group("retained/build");
+    group.sample_size(10);
+    for devices in DEVICES.iter() {
+        let topics = device_topics(*devices, SENSORS);
+        group.throughput(Throughput::Elements(topics.len() as u64));
+        group.bench_with_input(BenchmarkId::new("btree", devices), &topics, |b, topics| {
+            b.iter_batched(BTreeStore::new, |s| store(s, topics, &payload), BatchSize::LargeInput)
+        });
+
+        group.bench_with_input(BenchmarkId::new("trie", devices), &topics, |b, topics| {
+            b.iter_batched(TrieStore::new, |s| store(s, topics, &payload), BatchSize::LargeInput)
+        });
+
+        // Topics arrive sorted here which is the best case for inserts into a sorted vec
+        group.bench_with_input(BenchmarkId::new("sortedvec", devices), &topics, |b, topics| {
+            b.iter_batched(SortedVecStore::new, |s| store(s, topics, &payload), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+/// What a new subscriber pays to get its retained messages. Throughput is the
+/// number of messages handed back
+fn scan(c: &mut Criterion) {
+    let payload = Bytes::from(vec![1u8; PAYLOAD_SIZE]);
+    for devices in DEVICES.iter() {
+        let topics = device_topics(*devices, SENSORS);
+        let btree = store(BTreeStore::new(), &topics, &payload);
+        let trie = store(TrieStore::new(), &topics, &payload);
+        let sortedvec = store(SortedVecStore::new(), &topics, &payload);
+
+        let device = format!("hello/device{}", devices / 2);
+        let filters = [
+            ("device", format!("{}/#", device)),
+            ("sensor", "hello/+/sensor3".to_owned()),
+            ("all", "hello/#".to_owned()),
+        ];
+
+        for (name, filter) in filters.iter() {
+            let mut group = c.benchmark_group(format!("retained/{}", name));
+            group.throughput(Throughput::Elements(btree.matches(filter).len() as u64));
+            bench_store(&mut group, "btree", *devices, &btree, filter);
+            bench_store(&mut group, "trie", *devices, &trie, filter);
+            bench_store(&mut group, "sortedvec", *devices, &sortedvec, filter);
+            group.finish();
+        }
+
+        let topic = format!("{}/sensor3", device);
+        let mut group = c.benchmark_group("retained/get");
+        group.throughput(Throughput::Elements(1));
+        group.bench_with_input(BenchmarkId::new("btree", devices), &btree, |b, s| b.iter(|| s.get(&topic).is_some()));
+        group.bench_with_input(BenchmarkId::new("trie", devices), &trie, |b, s| b.iter(|| s.get(&topic).is_some()));
+        group.bench_with_input(BenchmarkId::new("sortedvec", devices), &sortedvec, |b, s| b.iter(|| s.get(&topic).is_some()));
+        group.finish();
+
+        let prefix = format!("{}/", device);
+        let mut group = c.benchmark_group("retained/prefix");
+        group.throughput(Throughput::Elements(btree.prefix(&prefix).len() as u64));
+        group.bench_with_input(BenchmarkId::new("btree", devices), &btree, |b, s| b.iter(|| s.prefix(&prefix).len()));
+        group.bench_with_input(BenchmarkId::new("trie", devices), &trie, |b, s| b.iter(|| s.prefix(&prefix).len()));
+        group.bench_with_input(BenchmarkId::new("sortedvec", devices), &sortedvec, |b, s| b.iter(|| s.prefix(&prefix).len()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, build, scan);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use bytes::Bytes;
+use collections::concurrent::Sharded;
+use collections::dataset::{self, Dataset};
+use collections::retained::{BTreeStore, RetainStore, SortedVecStore, TrieStore};
+use common::alloc::{self, CountingAllocator, Footprint};
+use dashmap::DashMap;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+
+fn main() {
+    for size in SIZES.iter() {
+        let dataset = Dataset::new(*size, 0);
+
+        let (v, footprint) = alloc::measure(|| dataset.vec());
+        report("vec", &dataset, footprint);
+        drop(v);
+
+        let (map, footprint) = alloc::measure(|| dataset.map());
+        report("map", &dataset, footprint);
+        drop(map);
+
+        let (map, footprint) = alloc::measure(|| dataset.indexmap());
+        report("indexmap", &dataset, footprint);
+        drop(map);
+
+        let (map, footprint) = alloc::measure(|| dataset.seahash_map());
+        report("seahash_map", &dataset, footprint);
+        drop(map);
+
+        // Interned topics cost the interner once plus an id keyed map per
+        // collection which uses them
+        let (ids, footprint) = alloc::measure(|| dataset.interner());
+        report("interner", &dataset, footprint);
+        drop(ids);
+
+        let (map, footprint) = alloc::measure(|| dataset.id_map());
+        report("id_map", &dataset, footprint);
+        drop(map);
+
+        let (map, footprint) = alloc::measure(|| {
+            let map = RwLock::new(HashMap::new());
+            dataset.populate(&map);
+            map
+        });
+        report("rwlock", &dataset, footprint);
+        drop(map);
+
+        let (map, footprint) = alloc::measure(|| {
+            let map = Sharded::new(64);
+            dataset.populate(&map);
+            map
+        });
+        report("sharded", &dataset, footprint);
+        drop(map);
+
+        let (map, footprint) = alloc::measure(|| {
+            let map = DashMap::new();
+            dataset.populate(&map);
+            map
+        });
+        report("dashmap", &dataset, footprint);
+        drop(map);
+
+        // Includes whatever flurry retired while resizing but hasn't freed
+        // yet. Its collector frees retired tables and nodes in batches of 120
+        // once no guard can see them, so up to a batch of garbage is counted
+        let (map, footprint) = alloc::measure(|| {
+            let map = flurry::HashMap::new();
+            dataset.populate(&map);
+            map
+        });
+        report("flurry", &dataset, footprint);
+        drop(map);
+    }
+
+    // Retained stores share one payload so only the topics and structure count
+    let payload = Bytes::from(vec![1u8; 64]);
+    for size in SIZES.iter() {
+        let topics = dataset::device_topics(size / 10, 10);
+
+        let (store, footprint) = alloc::measure(|| retain(BTreeStore::new(), &topics, &payload));
+        report_entries("retained_btree", store.len(), footprint);
+        drop(store);
+
+        let (store, footprint) = alloc::measure(|| retain(TrieStore::new(), &topics, &payload));
+        report_entries("retained_trie", store.len(), footprint);
+        drop(store);
+
+        let (store, footprint) = alloc::measure(|| retain(SortedVecStore::new(), &topics, &payload));
+        report_entries("retained_sortedvec", store.len(), footprint);
+        drop(store);
+    }
+}
+
+fn retain<S: RetainStore>(mut store: S, topics: &[String], payload: &Bytes) -> S {
+    for topic in topics.iter() {
+        store.retain(topic, payload.clone());
+    }
+
+    store
+}
+
+fn report(name: &str, dataset: &Dataset, footprint: Footprint) {
+    report_entries(name, dataset.len(), footprint)
+}
+
+fn report_entries(name: &str, entries: usize, footprint: Footprint) {
+    let live_mb = footprint.live_bytes as f64 / 1024.0 / 1024.0;
+    let allocated_mb = footprint.allocated as f64 / 1024.0 / 1024.0;
+    let per_entry = footprint.live_bytes as f64 / entries as f64;
+    println!(
+        "{}. Entries = {}, Live = {:.2} MB in {} allocations ({:.1} bytes/entry), Allocated = {:.2} MB in {} allocations",
+        name,
+        entries,
+        live_mb,
+        footprint.live_allocations,
+        per_entry,
+        allocated_mb,
+        footprint.allocations
+    );
+}
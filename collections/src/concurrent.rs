@@ -0,0 +1,91 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::RwLock;
+
+use dashmap::DashMap;
+
+/// Maps which can be shared between connection tasks behind an `Arc`. Writes
+/// update an existing subscription in place and only allocate a key when the
+/// topic is new, which is what a broker's subscription table does
+pub trait ConcurrentMap: Send + Sync {
+    fn get(&self, key: &str) -> Option<u64>;
+    fn update(&self, key: &str, value: u64);
+}
+
+impl ConcurrentMap for RwLock<HashMap<String, u64>> {
+    fn get(&self, key: &str) -> Option<u64> {
+        self.read().unwrap().get(key).copied()
+    }
+
+    fn update(&self, key: &str, value: u64) {
+        let mut map = self.write().unwrap();
+        match map.get_mut(key) {
+            Some(v) => *v = value,
+            None => {
+                map.insert(key.to_owned(), value);
+            }
+        }
+    }
+}
+
+/// `RwLock<HashMap>` split into shards picked by key hash so that writers only
+/// contend with readers of the same shard
+pub struct Sharded {
+    hasher: RandomState,
+    shards: Vec<RwLock<HashMap<String, u64>>>,
+}
+
+impl Sharded {
+    pub fn new(shards: usize) -> Sharded {
+        let shards = (0..shards).map(|_| RwLock::new(HashMap::new())).collect();
+        Sharded {
+            hasher: RandomState::new(),
+            shards,
+        }
+    }
+
+    fn shard(&self, key: &str) -> &RwLock<HashMap<String, u64>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        &self.shards[index]
+    }
+}
+
+impl ConcurrentMap for Sharded {
+    fn get(&self, key: &str) -> Option<u64> {
+        self.shard(key).get(key)
+    }
+
+    fn update(&self, key: &str, value: u64) {
+        self.shard(key).update(key, value)
+    }
+}
+
+impl ConcurrentMap for DashMap<String, u64> {
+    fn get(&self, key: &str) -> Option<u64> {
+        DashMap::get(self, key).map(|v| *v)
+    }
+
+    fn update(&self, key: &str, value: u64) {
+        match self.get_mut(key) {
+            Some(mut v) => *v = value,
+            None => {
+                self.insert(key.to_owned(), value);
+            }
+        }
+    }
+}
+
+/// Lock free map with epoch based memory reclamation
+impl ConcurrentMap for flurry::HashMap<String, u64> {
+    fn get(&self, key: &str) -> Option<u64> {
+        self.pin().get(key).copied()
+    }
+
+    fn update(&self, key: &str, value: u64) {
+        let map = self.pin();
+        if map.compute_if_present(key, |_, _| Some(value)).is_none() {
+            map.insert(key.to_owned(), value);
+        }
+    }
+}
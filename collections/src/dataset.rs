@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use indexmap::IndexMap;
+use fasthash::RandomState;
+use fasthash::sea::Hash64;
+use common::workload::{self, Pattern};
+
+use crate::concurrent::ConcurrentMap;
+
+/// Fixed seed so that every run, and every structure in a run, sees the same lookups
+pub const SEED: u64 = 0x6e75_6d62;
+
+/// Topic used for entry `i`. Same shape as the keys of the original `access_map_1` bench
+pub fn topic(i: usize) -> String {
+    "hello/world".to_owned() + &i.to_string()
+}
+
+/// Hierarchical topics `hello/device{d}/sensor{s}`, sorted by device, for the
+/// benches which scan with prefixes and wildcards
+pub fn device_topics(devices: usize, sensors: usize) -> Vec<String> {
+    let mut topics = Vec::with_capacity(devices * sensors);
+    for d in 0..devices {
+        for s in 0..sensors {
+            topics.push(format!("hello/device{}/sensor{}", d, s));
+        }
+    }
+
+    topics
+}
+
+/// Keys and lookup indices shared by all the collection benchmarks. Build every
+/// structure under test from the same `Dataset` so that they hold identical keys
+/// and are queried in the same order
+pub struct Dataset {
+    pub topics: Vec<String>,
+    pub lookups: Vec<usize>,
+}
+
+impl Dataset {
+    /// Dataset with uniformly distributed lookups
+    pub fn new(size: usize, lookups: usize) -> Dataset {
+        Dataset::with_pattern(size, lookups, Pattern::Uniform)
+    }
+
+    /// Dataset whose lookups follow `pattern`
+    pub fn with_pattern(size: usize, lookups: usize, pattern: Pattern) -> Dataset {
+        let topics = (0..size).map(topic).collect();
+        let mut dataset = Dataset { topics, lookups: Vec::new() };
+        dataset.set_lookups(pattern, lookups);
+        dataset
+    }
+
+    /// Replaces the lookups with `count` new ones following `pattern`. Lets
+    /// structures built once be queried under different access patterns
+    pub fn set_lookups(&mut self, pattern: Pattern, count: usize) {
+        self.lookups = workload::generate_indices(pattern, self.len(), count, SEED);
+    }
+
+    pub fn len(&self) -> usize {
+        self.topics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty()
+    }
+
+    /// Topics to look up, in lookup order
+    pub fn keys(&self) -> Vec<&str> {
+        self.lookups.iter().map(|i| self.topics[*i].as_str()).collect()
+    }
+
+    pub fn vec(&self) -> Vec<Option<u64>> {
+        vec![Some(1); self.len()]
+    }
+
+    pub fn map(&self) -> HashMap<String, u64> {
+        let mut map = HashMap::with_capacity(self.len());
+        for topic in self.topics.iter() {
+            map.insert(topic.clone(), 1u64);
+        }
+
+        map
+    }
+
+    pub fn indexmap(&self) -> IndexMap<String, u64> {
+        let mut map = IndexMap::with_capacity(self.len());
+        for topic in self.topics.iter() {
+            map.insert(topic.clone(), 1u64);
+        }
+
+        map
+    }
+
+    pub fn seahash_map(&self) -> HashMap<String, u64, RandomState<Hash64>> {
+        let s = RandomState::<Hash64>::new();
+        let mut map = HashMap::with_capacity_and_hasher(self.len(), s);
+        for topic in self.topics.iter() {
+            map.insert(topic.clone(), 1u64);
+        }
+
+        map
+    }
+
+    /// Dense id for every topic, in topic order. Interning once lets every
+    /// other collection key on the id instead of holding its own copy
+    pub fn interner(&self) -> HashMap<String, u32> {
+        let mut ids = HashMap::with_capacity(self.len());
+        for (id, topic) in self.topics.iter().enumerate() {
+            ids.insert(topic.clone(), id as u32);
+        }
+
+        ids
+    }
+
+    /// Map keyed by the ids from `interner`
+    pub fn id_map(&self) -> HashMap<u32, u64> {
+        let mut map = HashMap::with_capacity(self.len());
+        for id in 0..self.len() {
+            map.insert(id as u32, 1u64);
+        }
+
+        map
+    }
+
+    /// Fills a shared map with every topic
+    pub fn populate<M: ConcurrentMap>(&self, map: &M) {
+        for (i, topic) in self.topics.iter().enumerate() {
+            map.update(topic, i as u64);
+        }
+    }
+}
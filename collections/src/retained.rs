@@ -0,0 +1,493 @@
+use std::collections::btree_map::{self, BTreeMap};
+use std::ops::Bound;
+
+use bytes::Bytes;
+
+/// Last retained payload of every topic. Retaining an empty payload clears
+/// the topic, like an MQTT publish with retain flag and no payload does
+pub trait RetainStore {
+    fn retain(&mut self, topic: &str, payload: Bytes);
+    fn get(&self, topic: &str) -> Option<&Bytes>;
+    /// Every retained message whose topic starts with `prefix`, sorted by topic
+    fn prefix(&self, prefix: &str) -> Vec<(&str, &Bytes)>;
+    /// Every retained message matching the MQTT topic filter, sorted by topic
+    fn matches(&self, filter: &str) -> Vec<(&str, &Bytes)>;
+}
+
+/// MQTT topic filter matching. `+` matches exactly one level and a trailing `#`
+/// matches the parent and any number of levels below it. Wildcards at the
+/// first level don't match topics starting with `$`
+pub fn matches(topic: &str, filter: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let mut topic = topic.split('/');
+    let mut filter = filter.split('/');
+    loop {
+        match (filter.next(), topic.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => continue,
+            (Some(f), Some(t)) if f == t => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Part of the filter before its first wildcard level, without the trailing
+/// separator so that `hello/#` still finds `hello`
+fn literal_prefix(filter: &str) -> &str {
+    let mut end = 0;
+    for level in filter.split('/') {
+        if level == "+" || level == "#" {
+            return &filter[..end.max(1) - 1];
+        }
+
+        end += level.len() + 1;
+    }
+
+    filter
+}
+
+#[derive(Default)]
+pub struct BTreeStore {
+    map: BTreeMap<String, Bytes>,
+}
+
+impl BTreeStore {
+    pub fn new() -> BTreeStore {
+        BTreeStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Entries from the first topic not smaller than `prefix`
+    fn from(&self, prefix: &str) -> btree_map::Range<'_, String, Bytes> {
+        self.map.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+    }
+}
+
+impl RetainStore for BTreeStore {
+    fn retain(&mut self, topic: &str, payload: Bytes) {
+        if payload.is_empty() {
+            self.map.remove(topic);
+            return;
+        }
+
+        match self.map.get_mut(topic) {
+            Some(p) => *p = payload,
+            None => {
+                self.map.insert(topic.to_owned(), payload);
+            }
+        }
+    }
+
+    fn get(&self, topic: &str) -> Option<&Bytes> {
+        self.map.get(topic)
+    }
+
+    fn prefix(&self, prefix: &str) -> Vec<(&str, &Bytes)> {
+        self.from(prefix)
+            .take_while(|(topic, _)| topic.starts_with(prefix))
+            .map(|(topic, payload)| (topic.as_str(), payload))
+            .collect()
+    }
+
+    fn matches(&self, filter: &str) -> Vec<(&str, &Bytes)> {
+        let prefix = literal_prefix(filter);
+        self.from(prefix)
+            .take_while(|(topic, _)| topic.starts_with(prefix))
+            .filter(|(topic, _)| matches(topic, filter))
+            .map(|(topic, payload)| (topic.as_str(), payload))
+            .collect()
+    }
+}
+
+/// Topics kept sorted in a single `Vec`. Cheap scans and small footprint at the
+/// cost of O(n) inserts
+#[derive(Default)]
+pub struct SortedVecStore {
+    entries: Vec<(String, Bytes)>,
+}
+
+impl SortedVecStore {
+    pub fn new() -> SortedVecStore {
+        SortedVecStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn search(&self, topic: &str) -> Result<usize, usize> {
+        self.entries.binary_search_by(|(t, _)| t.as_str().cmp(topic))
+    }
+
+    /// Entries from the first topic not smaller than `prefix`
+    fn from(&self, prefix: &str) -> &[(String, Bytes)] {
+        let start = match self.search(prefix) {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+
+        &self.entries[start..]
+    }
+}
+
+impl RetainStore for SortedVecStore {
+    fn retain(&mut self, topic: &str, payload: Bytes) {
+        match self.search(topic) {
+            Ok(i) if payload.is_empty() => {
+                self.entries.remove(i);
+            }
+            Ok(i) => self.entries[i].1 = payload,
+            Err(_) if payload.is_empty() => (),
+            Err(i) => self.entries.insert(i, (topic.to_owned(), payload)),
+        }
+    }
+
+    fn get(&self, topic: &str) -> Option<&Bytes> {
+        match self.search(topic) {
+            Ok(i) => Some(&self.entries[i].1),
+            Err(_) => None,
+        }
+    }
+
+    fn prefix(&self, prefix: &str) -> Vec<(&str, &Bytes)> {
+        self.from(prefix)
+            .iter()
+            .take_while(|(topic, _)| topic.starts_with(prefix))
+            .map(|(topic, payload)| (topic.as_str(), payload))
+            .collect()
+    }
+
+    fn matches(&self, filter: &str) -> Vec<(&str, &Bytes)> {
+        let prefix = literal_prefix(filter);
+        self.from(prefix)
+            .iter()
+            .take_while(|(topic, _)| topic.starts_with(prefix))
+            .filter(|(topic, _)| matches(topic, filter))
+            .map(|(topic, payload)| (topic.as_str(), payload))
+            .collect()
+    }
+}
+
+/// One node per topic level. Children are ordered, so walks come out sorted
+/// level by level. That only differs from topic order for levels containing
+/// characters that sort before `/` (`a/b` comes before `a-c`), so scans sort
+/// what they collect. Nodes holding a message keep the full topic to hand it
+/// back from scans without rebuilding it
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    retained: Option<(String, Bytes)>,
+}
+
+impl Node {
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.retained.is_none()
+    }
+
+    /// Clears the message at `levels` below this node and prunes the nodes
+    /// left empty. Returns true if this node is empty afterwards
+    fn remove<'a>(&mut self, mut levels: impl Iterator<Item = &'a str>) -> bool {
+        match levels.next() {
+            Some(level) => {
+                if let Some(child) = self.children.get_mut(level) {
+                    if child.remove(levels) {
+                        self.children.remove(level);
+                    }
+                }
+            }
+            None => self.retained = None,
+        }
+
+        self.is_empty()
+    }
+
+    fn collect<'a>(&'a self, out: &mut Vec<(&'a str, &'a Bytes)>) {
+        if let Some((topic, payload)) = &self.retained {
+            out.push((topic.as_str(), payload));
+        }
+
+        for child in self.children.values() {
+            child.collect(out);
+        }
+    }
+
+    fn matches<'a>(&'a self, filter: &[&str], root: bool, out: &mut Vec<(&'a str, &'a Bytes)>) {
+        let (level, rest) = match filter.split_first() {
+            Some(v) => v,
+            None => {
+                if let Some((topic, payload)) = &self.retained {
+                    out.push((topic.as_str(), payload));
+                }
+
+                return;
+            }
+        };
+
+        match *level {
+            "#" => {
+                // `#` also matches the parent level
+                if !root {
+                    if let Some((topic, payload)) = &self.retained {
+                        out.push((topic.as_str(), payload));
+                    }
+                }
+
+                for (name, child) in self.children.iter() {
+                    if !(root && name.starts_with('$')) {
+                        child.collect(out);
+                    }
+                }
+            }
+            "+" => {
+                for (name, child) in self.children.iter() {
+                    if !(root && name.starts_with('$')) {
+                        child.matches(rest, false, out);
+                    }
+                }
+            }
+            level => {
+                if let Some(child) = self.children.get(level) {
+                    child.matches(rest, false, out);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TrieStore {
+    root: Node,
+    len: usize,
+}
+
+impl TrieStore {
+    pub fn new() -> TrieStore {
+        TrieStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn node(&self, topic: &str) -> Option<&Node> {
+        let mut node = &self.root;
+        for level in topic.split('/') {
+            node = node.children.get(level)?;
+        }
+
+        Some(node)
+    }
+}
+
+impl RetainStore for TrieStore {
+    fn retain(&mut self, topic: &str, payload: Bytes) {
+        if payload.is_empty() {
+            if self.get(topic).is_some() {
+                self.root.remove(topic.split('/'));
+                self.len -= 1;
+            }
+
+            return;
+        }
+
+        let mut node = &mut self.root;
+        for level in topic.split('/') {
+            // Avoids allocating the level when the node already exists
+            if !node.children.contains_key(level) {
+                node.children.insert(level.to_owned(), Node::default());
+            }
+
+            node = node.children.get_mut(level).unwrap();
+        }
+
+        match &mut node.retained {
+            Some((_, p)) => *p = payload,
+            None => {
+                node.retained = Some((topic.to_owned(), payload));
+                self.len += 1;
+            }
+        }
+    }
+
+    fn get(&self, topic: &str) -> Option<&Bytes> {
+        self.node(topic)?.retained.as_ref().map(|(_, payload)| payload)
+    }
+
+    fn prefix(&self, prefix: &str) -> Vec<(&str, &Bytes)> {
+        let mut out = Vec::new();
+
+        // Walk down the complete levels. The last one may be partial, so every
+        // child starting with it is part of the scan
+        let (parent, partial) = match prefix.rfind('/') {
+            Some(i) => (Some(&prefix[..i]), &prefix[i + 1..]),
+            None => (None, prefix),
+        };
+
+        let node = match parent {
+            Some(parent) => match self.node(parent) {
+                Some(node) => node,
+                None => return out,
+            },
+            None => &self.root,
+        };
+
+        let range = node.children.range::<str, _>((Bound::Included(partial), Bound::Unbounded));
+        for (name, child) in range {
+            if !name.starts_with(partial) {
+                break;
+            }
+
+            child.collect(&mut out);
+        }
+
+        sort(&mut out);
+        out
+    }
+
+    fn matches(&self, filter: &str) -> Vec<(&str, &Bytes)> {
+        let filter: Vec<&str> = filter.split('/').collect();
+        let mut out = Vec::new();
+        self.root.matches(&filter, true, &mut out);
+        sort(&mut out);
+        out
+    }
+}
+
+/// Sorts trie scans by topic. They come out close to sorted, which the stable
+/// sort handles in about linear time
+fn sort(out: &mut [(&str, &Bytes)]) {
+    out.sort_by_key(|(topic, _)| *topic);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOPICS: [&str; 12] = [
+        "a",
+        "a/b",
+        "a/b/c",
+        "a/c",
+        "a-c",
+        "a/b-c",
+        "ab",
+        "b/x/y",
+        "/a",
+        "$SYS/uptime",
+        "$SYS/a/b",
+        "a/$SYS",
+    ];
+
+    fn load<S: RetainStore>(mut store: S) -> S {
+        for (i, topic) in TOPICS.iter().enumerate() {
+            store.retain(topic, Bytes::from(vec![i as u8 + 1]));
+        }
+
+        store
+    }
+
+    fn topics(out: Vec<(&str, &Bytes)>) -> Vec<String> {
+        out.into_iter().map(|(topic, _)| topic.to_owned()).collect()
+    }
+
+    /// Every store's scan, checked to be the same across stores
+    fn scan(stores: &(BTreeStore, SortedVecStore, TrieStore), f: impl Fn(&dyn RetainStore) -> Vec<(&str, &Bytes)>) -> Vec<String> {
+        let btree = topics(f(&stores.0));
+        assert_eq!(btree, topics(f(&stores.1)), "sorted vec differs from btree");
+        assert_eq!(btree, topics(f(&stores.2)), "trie differs from btree");
+        btree
+    }
+
+    fn stores() -> (BTreeStore, SortedVecStore, TrieStore) {
+        (load(BTreeStore::new()), load(SortedVecStore::new()), load(TrieStore::new()))
+    }
+
+    #[test]
+    fn matches_agree() {
+        let stores = stores();
+        let cases: [(&str, &[&str]); 11] = [
+            ("a", &["a"]),
+            ("+", &["a", "a-c", "ab"]),
+            ("+/+", &["/a", "a/$SYS", "a/b", "a/b-c", "a/c"]),
+            ("a/+", &["a/$SYS", "a/b", "a/b-c", "a/c"]),
+            ("+/b/+", &["a/b/c"]),
+            ("#", &["/a", "a", "a-c", "a/$SYS", "a/b", "a/b-c", "a/b/c", "a/c", "ab", "b/x/y"]),
+            ("a/#", &["a", "a/$SYS", "a/b", "a/b-c", "a/b/c", "a/c"]),
+            ("a/b/#", &["a/b", "a/b/c"]),
+            ("$SYS/#", &["$SYS/a/b", "$SYS/uptime"]),
+            ("$SYS/+", &["$SYS/uptime"]),
+            ("b/+/y/#", &["b/x/y"]),
+        ];
+
+        for (filter, expected) in cases.iter() {
+            assert_eq!(scan(&stores, |s| s.matches(filter)), *expected, "filter {}", filter);
+            let all: Vec<&str> = {
+                let mut all: Vec<&str> = TOPICS.iter().copied().filter(|t| matches(t, filter)).collect();
+                all.sort();
+                all
+            };
+
+            assert_eq!(all, *expected, "matches() for filter {}", filter);
+        }
+    }
+
+    #[test]
+    fn prefix_agree() {
+        let stores = stores();
+        let cases: [(&str, &[&str]); 6] = [
+            ("a", &["a", "a-c", "a/$SYS", "a/b", "a/b-c", "a/b/c", "a/c", "ab"]),
+            ("a/", &["a/$SYS", "a/b", "a/b-c", "a/b/c", "a/c"]),
+            ("a/b", &["a/b", "a/b-c", "a/b/c"]),
+            ("$", &["$SYS/a/b", "$SYS/uptime"]),
+            ("", &["$SYS/a/b", "$SYS/uptime", "/a", "a", "a-c", "a/$SYS", "a/b", "a/b-c", "a/b/c", "a/c", "ab", "b/x/y"]),
+            ("c", &[]),
+        ];
+
+        for (prefix, expected) in cases.iter() {
+            assert_eq!(scan(&stores, |s| s.prefix(prefix)), *expected, "prefix {}", prefix);
+        }
+    }
+
+    #[test]
+    fn empty_payload_deletes() {
+        let mut stores = stores();
+        for topic in ["a/b", "a", "$SYS/uptime", "not/retained"].iter() {
+            stores.0.retain(topic, Bytes::new());
+            stores.1.retain(topic, Bytes::new());
+            stores.2.retain(topic, Bytes::new());
+        }
+
+        assert_eq!(stores.0.len(), TOPICS.len() - 3);
+        assert_eq!(stores.1.len(), TOPICS.len() - 3);
+        assert_eq!(stores.2.len(), TOPICS.len() - 3);
+        assert!(stores.2.get("a/b").is_none());
+        assert_eq!(stores.2.get("a/b/c"), Some(&Bytes::from(vec![3])));
+        assert_eq!(scan(&stores, |s| s.matches("a/#")), ["a/$SYS", "a/b-c", "a/b/c", "a/c"]);
+        assert_eq!(scan(&stores, |s| s.matches("$SYS/#")), ["$SYS/a/b"]);
+        assert_eq!(scan(&stores, |s| s.prefix("a/b")), ["a/b-c", "a/b/c"]);
+
+        // Replacing keeps one entry with the new payload
+        stores.2.retain("a/c", Bytes::from_static(b"new"));
+        assert_eq!(stores.2.get("a/c"), Some(&Bytes::from_static(b"new")));
+        assert_eq!(stores.2.len(), TOPICS.len() - 3);
+    }
+}
@@ -0,0 +1,138 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Keeps each counter on its own cache line so that threads allocating and
+/// freeing at the same time don't also fight over the line
+#[repr(align(128))]
+struct Counter(AtomicUsize);
+
+impl Deref for Counter {
+    type Target = AtomicUsize;
+
+    fn deref(&self) -> &AtomicUsize {
+        &self.0
+    }
+}
+
+static COUNTING: AtomicBool = AtomicBool::new(true);
+static ALLOCATED: Counter = Counter(AtomicUsize::new(0));
+static ALLOCATIONS: Counter = Counter(AtomicUsize::new(0));
+static LIVE_BYTES: Counter = Counter(AtomicUsize::new(0));
+static LIVE_ALLOCATIONS: Counter = Counter(AtomicUsize::new(0));
+
+/// System allocator which keeps count of what goes through it, until `stop`
+/// is called. Counters are process wide. Install it in a binary with
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: common::alloc::CountingAllocator = common::alloc::CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+            LIVE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        if !COUNTING.load(Ordering::Relaxed) {
+            return;
+        }
+
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        LIVE_ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() && COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+            LIVE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        ptr
+    }
+
+    /// Counted as a fresh allocation of `new_size` replacing the old one
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let ptr = System.realloc(ptr, layout, new_size);
+        if !ptr.is_null() && COUNTING.load(Ordering::Relaxed) {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        ptr
+    }
+}
+
+/// Stops counting for the rest of the process, leaving a load per allocation
+/// over the system allocator. For binaries which only need counts in some
+/// runs. It can't be turned back on, as frees of allocations made meanwhile
+/// would come off counts that never had them
+pub fn stop() {
+    COUNTING.store(false, Ordering::Relaxed);
+}
+
+/// Snapshot of the allocator counters
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    /// Bytes allocated since start, including the ones freed since
+    pub allocated: usize,
+    /// Allocations since start, including the ones freed since
+    pub allocations: usize,
+    /// Bytes currently allocated
+    pub live_bytes: usize,
+    /// Allocations currently alive
+    pub live_allocations: usize,
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        allocated: ALLOCATED.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        live_allocations: LIVE_ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// What a closure allocated. `live_*` is what is still held once it returns,
+/// i.e. the footprint of whatever it built and returned
+#[derive(Debug, Clone, Copy)]
+pub struct Footprint {
+    pub allocated: usize,
+    pub allocations: usize,
+    pub live_bytes: isize,
+    pub live_allocations: isize,
+}
+
+/// Runs `f` and reports what it allocated. Only meaningful when
+/// `CountingAllocator` is the global allocator and no other thread is
+/// allocating at the same time
+pub fn measure<T, F: FnOnce() -> T>(f: F) -> (T, Footprint) {
+    let before = stats();
+    let out = f();
+    let after = stats();
+
+    let footprint = Footprint {
+        allocated: after.allocated - before.allocated,
+        allocations: after.allocations - before.allocations,
+        live_bytes: after.live_bytes as isize - before.live_bytes as isize,
+        live_allocations: after.live_allocations as isize - before.live_allocations as isize,
+    };
+
+    (out, footprint)
+}
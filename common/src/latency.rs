@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use hdrhistogram::Histogram;
+
+/// Histogram of latencies, in nanoseconds to 3 significant digits. Grows to
+/// fit whatever is recorded
+pub struct Latencies {
+    histogram: Histogram<u64>,
+}
+
+impl Latencies {
+    pub fn new() -> Latencies {
+        Latencies {
+            histogram: Histogram::new(3).unwrap(),
+        }
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.histogram.record(latency.as_nanos() as u64).unwrap();
+    }
+
+    pub fn add(&mut self, other: &Latencies) {
+        self.histogram.add(&other.histogram).unwrap();
+    }
+
+    pub fn len(&self) -> u64 {
+        self.histogram.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.histogram.is_empty()
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos(self.histogram.mean() as u64)
+    }
+
+    /// p50, p90, p99, p99.9 and max
+    pub fn percentiles(&self) -> String {
+        let at = |q| Duration::from_nanos(self.histogram.value_at_quantile(q));
+        format!(
+            "p50 = {:?}, p90 = {:?}, p99 = {:?}, p99.9 = {:?}, max = {:?}",
+            at(0.5),
+            at(0.9),
+            at(0.99),
+            at(0.999),
+            Duration::from_nanos(self.histogram.max())
+        )
+    }
+
+    pub fn print(&self, what: &str) {
+        println!("{} {}", what, self.percentiles());
+    }
+}
+
+impl Default for Latencies {
+    fn default() -> Latencies {
+        Latencies::new()
+    }
+}
+
+/// Round trips of requests which are acked in the order they were sent, so
+/// that each ack is for the oldest request still waiting
+pub struct InOrder {
+    sent: VecDeque<Instant>,
+    rtts: Latencies,
+}
+
+impl InOrder {
+    pub fn new() -> InOrder {
+        InOrder {
+            sent: VecDeque::new(),
+            rtts: Latencies::new(),
+        }
+    }
+
+    pub fn sent(&mut self) {
+        self.sent.push_back(Instant::now());
+    }
+
+    /// Records the round trip of the oldest request waiting. Returns acks so
+    /// far
+    pub fn acked(&mut self) -> usize {
+        let sent = self.sent.pop_front().expect("Ack without a request sent");
+        self.rtts.record(sent.elapsed());
+        self.rtts.len() as usize
+    }
+
+    pub fn rtts(self) -> Latencies {
+        self.rtts
+    }
+}
+
+impl Default for InOrder {
+    fn default() -> InOrder {
+        InOrder::new()
+    }
+}
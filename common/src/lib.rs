@@ -1,3 +1,11 @@
+pub mod alloc;
+pub mod latency;
+pub mod payload;
+pub mod workload;
+
+use std::fmt::Display;
+use std::str::FromStr;
+
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
@@ -11,7 +19,34 @@ pub fn generate_string(payload_size: usize) -> String {
 }
 
 pub fn generate_payload(payload_size: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    let payload: Vec<u8> = (0..payload_size).map(|_| rng.gen_range(0, 255)).collect();
+    let mut payload = vec![0; payload_size];
+    payload::Generator::new(payload::Fill::Random).fill(&mut payload);
     payload
 }
+
+/// Parses sizes and counts which can't be 0. For argh's `from_str_fn`
+pub fn nonzero<T>(value: &str) -> Result<T, String>
+where
+    T: FromStr + PartialEq + Default,
+    T::Err: Display,
+{
+    match value.parse() {
+        Ok(n) if n == T::default() => Err("should be more than 0".to_owned()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::nonzero;
+
+    #[test]
+    fn nonzero_rejects_zero() {
+        assert_eq!(nonzero::<usize>("4"), Ok(4));
+        assert_eq!(nonzero::<u16>("65535"), Ok(65535));
+        assert!(nonzero::<usize>("0").is_err());
+        assert!(nonzero::<u16>("65536").is_err());
+        assert!(nonzero::<usize>("-1").is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// What payload bytes look like
+#[derive(Debug, Clone, Copy)]
+pub enum Fill {
+    /// Random bytes which don't compress. Every fill is fresh, so they don't
+    /// dedupe either unless the caller writes the same buffer more than once
+    Random,
+    /// Random bytes in the first half of every 64 byte line and zeros in the
+    /// rest. Compresses to about half
+    Compressible,
+}
+
+/// Fills caller owned buffers with payload bytes, a block of random bytes at
+/// a time. Reusing one buffer keeps memory bounded for payloads of any size
+pub struct Generator {
+    rng: StdRng,
+    fill: Fill,
+}
+
+impl Generator {
+    pub fn new(fill: Fill) -> Generator {
+        Generator {
+            rng: StdRng::from_entropy(),
+            fill,
+        }
+    }
+
+    pub fn with_seed(fill: Fill, seed: u64) -> Generator {
+        Generator {
+            rng: StdRng::seed_from_u64(seed),
+            fill,
+        }
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        match self.fill {
+            Fill::Random => self.rng.fill_bytes(buf),
+            Fill::Compressible => {
+                for line in buf.chunks_mut(64) {
+                    let half = line.len().min(32);
+                    self.rng.fill_bytes(&mut line[..half]);
+                    for b in line[half..].iter_mut() {
+                        *b = 0;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+/// How keys of a keyspace are picked by a workload
+#[derive(Debug, Clone, Copy)]
+pub enum Pattern {
+    /// Every key is equally likely
+    Uniform,
+    /// Key of rank `k` is picked with probability proportional to `1 / k^theta`.
+    /// `theta` should be in (0, 1). YCSB uses 0.99
+    Zipfian(f64),
+    /// `ops` fraction of the picks go to the first `keys` fraction of the keyspace
+    Hotspot { keys: f64, ops: f64 },
+    /// Keys in order starting from a random key, wrapping around at the end
+    Sequential,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Uniform => write!(f, "uniform"),
+            Pattern::Zipfian(theta) => write!(f, "zipfian_{}", theta),
+            Pattern::Hotspot { keys, ops } => write!(f, "hotspot_{}_{}", keys, ops),
+            Pattern::Sequential => write!(f, "sequential"),
+        }
+    }
+}
+
+/// Zipfian distribution over `0..n` using the rejection free method from
+/// "Quickly Generating Billion-Record Synthetic Databases" (Gray et al.),
+/// which is also what YCSB uses. Setup is O(n), each sample is O(1)
+pub struct Zipfian {
+    n: usize,
+    theta: f64,
+    alpha: f64,
+    zetan: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+    pub fn new(n: usize, theta: f64) -> Zipfian {
+        assert!(n > 0, "Zipfian needs at least one key");
+        if theta <= 0.0 || theta >= 1.0 {
+            panic!("Zipfian theta should be in (0, 1). Got {}", theta)
+        }
+
+        let zetan: f64 = (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum();
+        let zeta2 = 1.0 + 0.5f64.powf(theta);
+        let alpha = 1.0 / (1.0 - theta);
+        let eta = (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta2 / zetan);
+
+        Zipfian { n, theta, alpha, zetan, eta }
+    }
+}
+
+impl Distribution<usize> for Zipfian {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            return 0;
+        }
+
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1;
+        }
+
+        let k = (self.n as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as usize;
+        k.min(self.n - 1)
+    }
+}
+
+/// Generates `count` indices into a keyspace of `size` keys following `pattern`.
+/// Same seed gives the same indices so that different structures can be
+/// compared under identical access sequences
+pub fn generate_indices(pattern: Pattern, size: usize, count: usize, seed: u64) -> Vec<usize> {
+    assert!(size > 0, "Can't pick indices into an empty keyspace");
+    let mut rng = StdRng::seed_from_u64(seed);
+    match pattern {
+        Pattern::Uniform => (0..count).map(|_| rng.gen_range(0, size)).collect(),
+        Pattern::Zipfian(theta) => {
+            let zipfian = Zipfian::new(size, theta);
+            (0..count).map(|_| zipfian.sample(&mut rng)).collect()
+        }
+        Pattern::Hotspot { keys, ops } => {
+            let hot = ((size as f64 * keys) as usize).max(1).min(size);
+            (0..count)
+                .map(|_| {
+                    if hot == size || rng.gen_bool(ops) {
+                        rng.gen_range(0, hot)
+                    } else {
+                        rng.gen_range(hot, size)
+                    }
+                })
+                .collect()
+        }
+        Pattern::Sequential => {
+            let start = rng.gen_range(0, size);
+            (0..count).map(|i| (start + i) % size).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Rank `k` (from 1) is picked about `1 / (k^theta * zetan)` of the time
+    #[test]
+    fn zipfian_skew() {
+        let (n, theta, count) = (1000, 0.99, 1_000_000);
+        let indices = generate_indices(Pattern::Zipfian(theta), n, count, 7);
+        let mut hits = vec![0usize; n];
+        for i in indices {
+            hits[i] += 1;
+        }
+
+        let zetan: f64 = (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum();
+        for rank in 1..=2 {
+            let expected = count as f64 / ((rank as f64).powf(theta) * zetan);
+            let got = hits[rank - 1] as f64;
+            let error = (got - expected).abs() / expected;
+            assert!(error < 0.02, "rank {} got {} picks, expected {}", rank, got, expected);
+        }
+
+        // Top 1% of the keys take a good part of the picks, unlike uniform
+        let top: usize = hits[..n / 100].iter().sum();
+        assert!(top > count / 3, "top 1% of keys got {} of {} picks", top, count);
+    }
+
+    #[test]
+    fn zipfian_single_key() {
+        assert_eq!(generate_indices(Pattern::Zipfian(0.99), 1, 100, 7), vec![0; 100]);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty keyspace")]
+    fn no_keys() {
+        generate_indices(Pattern::Zipfian(0.99), 0, 100, 7);
+    }
+}
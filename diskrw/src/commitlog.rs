@@ -0,0 +1,140 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+use diskrw::log::Log;
+use packetparse::{disassemble_header, Packet};
+
+use crate::fstype;
+use crate::payload::Payload;
+use crate::write::{report, Timings};
+use crate::{throughput, Config};
+
+/// Distinct frames to cycle through while appending
+const FRAMES: usize = 1024;
+
+/// Memory records read back at once can take. Large frames are read fewer at
+/// a time
+const READ_SIZE: usize = 64 * 1024 * 1024;
+
+/// Headers of QoS 1 PUBLISH frames with `payload_size` payloads, differing in
+/// pkid. Encoded up front so that encoding isn't part of the append timings.
+/// Each frame is appended as its header followed by the payload chunks, so
+/// the payload is never copied into a frame
+fn headers(config: &Config) -> Vec<Bytes> {
+    (0..FRAMES)
+        .map(|i| {
+            let packet = Packet {
+                topic: "hello/mqtt/log/test".to_owned(),
+                dup: false,
+                retain: false,
+                qos: 1,
+                pkid: (i % 65000 + 1) as u16,
+                payload: Bytes::new(),
+            };
+
+            let mut header = BytesMut::new();
+            disassemble_header(&packet, config.payload_size, &mut header);
+            header.freeze()
+        })
+        .collect()
+}
+
+/// Appends `count` PUBLISH frames to a segmented log syncing as per the fsync
+/// policy, reads them all back and then times recovery from a torn append
+pub fn commitlog(config: &Config, dir: &Path) {
+    let start = Instant::now();
+    let headers = headers(config);
+    let payload = Payload::new(config);
+    let frame_len = headers[0].len() + payload.size();
+    println!("generating data ............ took {:?}", start.elapsed());
+
+    let mut log = Log::open(dir, config.segment_size).unwrap();
+    let mut timings = Timings::new();
+    let mut size = 0;
+    let mut writes = 0;
+    let mut bytes = 0;
+
+    let mut parts = Vec::new();
+    let start = Instant::now();
+    for i in 0..config.count {
+        parts.clear();
+        parts.push(&headers[i % FRAMES][..]);
+        parts.extend(payload.chunks());
+
+        let append_start = Instant::now();
+        log.append_parts(&parts).unwrap();
+        timings.writes.record(append_start.elapsed());
+        size += frame_len;
+        writes += 1;
+        bytes += frame_len;
+
+        if config.fsync.due(writes, bytes) {
+            timings.syncs.record(sync(&mut log));
+            writes = 0;
+            bytes = 0;
+        }
+    }
+
+    if writes > 0 {
+        timings.syncs.record(sync(&mut log));
+    }
+
+    timings.elapsed = start.elapsed();
+    report("log append", size, &timings);
+    println!("segments = {}, filesystem = {}", log.segments(), fstype::current());
+
+    let start = Instant::now();
+    let batch = (READ_SIZE / frame_len).clamp(1, FRAMES);
+    let mut offset = 0;
+    let mut size = 0;
+    while offset < log.next_offset() {
+        let records = log.read(offset, batch).unwrap();
+        offset += records.len() as u64;
+        size += records.iter().map(|r| r.len()).sum::<usize>();
+    }
+
+    println!("log read throughput = {:.2} MB/s, filesystem = {}", throughput(size, start.elapsed()), fstype::current());
+    drop(log);
+
+    tear(dir, &headers[0]).unwrap();
+    let start = Instant::now();
+    let log = Log::open(dir, config.segment_size).unwrap();
+    let elapsed = start.elapsed();
+    if log.next_offset() != config.count as u64 {
+        panic!("Recovered {} records. Expected {}", log.next_offset(), config.count);
+    }
+
+    println!("log recovery took {:?}, filesystem = {}", elapsed, fstype::current());
+}
+
+/// Appends a header and the first half of `record` to the last segment of the
+/// log in `dir`, as a crash in the middle of an append would leave it
+fn tear(dir: &Path, record: &[u8]) -> io::Result<()> {
+    // Segments are named after their zero padded base offset
+    let mut last = None;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "segment") {
+            last = last.max(Some(path));
+        }
+    }
+
+    let last = last.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No segments"))?;
+    let mut file = OpenOptions::new().append(true).open(last)?;
+
+    // Recovery stops at the length, which claims more than is there, before
+    // ever checking the crc
+    file.write_all(&(record.len() as u32).to_be_bytes())?;
+    file.write_all(&[0; 4])?;
+    file.write_all(&record[..record.len() / 2])?;
+    file.sync_data()
+}
+
+fn sync(log: &mut Log) -> Duration {
+    let start = Instant::now();
+    log.sync().unwrap();
+    start.elapsed()
+}
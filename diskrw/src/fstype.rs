@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Magic numbers from statfs(2) which libc doesn't have
+#[cfg(target_os = "linux")]
+const RAMFS_MAGIC: u32 = 0x8584_58f6;
+#[cfg(target_os = "linux")]
+const ZFS_SUPER_MAGIC: u32 = 0x2fc1_2fc1;
+
+static FILESYSTEM: OnceLock<String> = OnceLock::new();
+
+/// Finds the filesystem of `dir` once for the run, so that every result can
+/// record it
+pub fn init(dir: &Path) -> &'static str {
+    FILESYSTEM.get_or_init(|| fstype(dir))
+}
+
+/// Filesystem the results are measured on
+pub fn current() -> &'static str {
+    FILESYSTEM.get().map_or("unknown", |fstype| fstype.as_str())
+}
+
+/// Name of the filesystem `path` is on
+#[cfg(target_os = "linux")]
+fn fstype(path: &Path) -> String {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let mut stat = MaybeUninit::<libc::statfs>::uninit();
+    let ret = unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) };
+    if ret != 0 {
+        return "unknown".to_owned();
+    }
+
+    let magic = unsafe { stat.assume_init() }.f_type;
+    let name = match magic {
+        libc::EXT4_SUPER_MAGIC => "ext4",
+        libc::XFS_SUPER_MAGIC => "xfs",
+        libc::BTRFS_SUPER_MAGIC => "btrfs",
+        libc::F2FS_SUPER_MAGIC => "f2fs",
+        libc::NFS_SUPER_MAGIC => "nfs",
+        libc::TMPFS_MAGIC => "tmpfs",
+        libc::OVERLAYFS_SUPER_MAGIC => "overlayfs",
+        libc::FUSE_SUPER_MAGIC => "fuse",
+        _ => match magic as u32 {
+            RAMFS_MAGIC => "ramfs",
+            ZFS_SUPER_MAGIC => "zfs",
+            magic => return format!("unknown ({:#x})", magic),
+        },
+    };
+
+    name.to_owned()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn fstype(_path: &Path) -> String {
+    "unknown".to_owned()
+}
+
+/// Filesystems which don't hit a disk, or add a layer over the one that does
+pub fn warn(fstype: &str) {
+    match fstype {
+        "tmpfs" | "ramfs" => println!("warning: {} is in memory. Numbers aren't of a disk", fstype),
+        "overlayfs" => println!("warning: overlayfs adds copy up costs over the disk. Use a volume"),
+        _ => (),
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+mod segment;
+
+use segment::Segment;
+
+/// Append-only log of records split into segment files of at most
+/// `max_segment_size` bytes. Every record gets an offset, counting up from 0
+/// across segments, which it can be read back from.
+///
+/// Records are `len (u32) | crc32 of len and payload (u32) | payload`. Opening
+/// a log recovers it after a crash by truncating the segment at the first
+/// torn or corrupted record and deleting the segments after it. The
+/// directory is synced whenever a segment is created or deleted so that
+/// segments don't come and go after a crash
+pub struct Log {
+    dir: PathBuf,
+    max_segment_size: u64,
+    segments: Vec<Segment>,
+}
+
+impl Log {
+    pub fn open<P: AsRef<Path>>(dir: P, max_segment_size: u64) -> io::Result<Log> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)?;
+
+        let mut bases = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if let Some(base) = segment::base_offset(&path) {
+                bases.push(base);
+            }
+        }
+
+        bases.sort_unstable();
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut bases = bases.into_iter();
+        while let Some(base) = bases.next() {
+            let (segment, complete) = Segment::recover(&dir, base)?;
+            let expected = segments.last().map_or(base, |s| s.next_offset());
+            if base != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Segment {} doesn't follow offset {}", base, expected),
+                ));
+            }
+
+            segments.push(segment);
+
+            // Records after a bad one can't be trusted
+            if !complete {
+                for base in bases.by_ref() {
+                    fs::remove_file(segment::path(&dir, base))?;
+                }
+
+                sync_dir(&dir)?;
+            }
+        }
+
+        if segments.is_empty() {
+            segments.push(Segment::create(&dir, 0)?);
+            sync_dir(&dir)?;
+        }
+
+        Ok(Log { dir, max_segment_size, segments })
+    }
+
+    /// Offset the next appended record will get
+    pub fn next_offset(&self) -> u64 {
+        self.active().next_offset()
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Appends a record and returns its offset. Rolls over to a new segment
+    /// when the record doesn't fit in the active one. The full segment is
+    /// synced first so that only the active segment can have a torn tail
+    pub fn append(&mut self, record: &[u8]) -> io::Result<u64> {
+        self.append_parts(&[record])
+    }
+
+    /// Appends one record made up of `parts` in order, without having to
+    /// put them together in memory first. Records bigger than a segment fail
+    /// with `InvalidInput`
+    pub fn append_parts(&mut self, parts: &[&[u8]]) -> io::Result<u64> {
+        let size = segment::record_size(parts);
+        if size > self.max_segment_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Record of {} bytes doesn't fit in a segment of {}", size, self.max_segment_size),
+            ));
+        }
+
+        let active = self.active();
+        if active.len() > 0 && active.size() + size > self.max_segment_size {
+            let next = active.next_offset();
+            self.active_mut().sync()?;
+            let segment = Segment::create(&self.dir, next)?;
+            sync_dir(&self.dir)?;
+            self.segments.push(segment);
+        }
+
+        self.active_mut().append(parts)
+    }
+
+    /// Reads up to `count` records starting at `offset`
+    pub fn read(&mut self, offset: u64, count: usize) -> io::Result<Vec<Vec<u8>>> {
+        if offset >= self.next_offset() && count > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Offset {} not in log", offset)));
+        }
+
+        let mut i = match self.segments.binary_search_by_key(&offset, |s| s.base()) {
+            Ok(i) => i,
+            Err(0) => {
+                let base = self.segments[0].base();
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Offset {} is before the first segment at {}", offset, base),
+                ));
+            }
+            Err(i) => i - 1,
+        };
+
+        let mut offset = offset;
+        let mut records = Vec::with_capacity(count);
+        while records.len() < count && i < self.segments.len() {
+            let segment = &mut self.segments[i];
+            while records.len() < count && offset < segment.next_offset() {
+                records.push(segment.read(offset)?);
+                offset += 1;
+            }
+
+            i += 1;
+        }
+
+        Ok(records)
+    }
+
+    /// Writes buffered records and waits for them to be on disk
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.active_mut().sync()
+    }
+
+    fn active(&self) -> &Segment {
+        self.segments.last().unwrap()
+    }
+
+    fn active_mut(&mut self) -> &mut Segment {
+        self.segments.last_mut().unwrap()
+    }
+}
+
+/// Makes creating and deleting files in `dir` durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened to be synced
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    use super::*;
+
+    /// Every record takes 8 + 10 bytes so 3 fit in a segment
+    const SEGMENT_SIZE: u64 = 60;
+
+    fn record(i: u64) -> Vec<u8> {
+        format!("record{:04}", i).into_bytes()
+    }
+
+    fn filled(dir: &Path, count: u64) -> Log {
+        let mut log = Log::open(dir, SEGMENT_SIZE).unwrap();
+        for i in 0..count {
+            assert_eq!(log.append(&record(i)).unwrap(), i);
+        }
+
+        log.sync().unwrap();
+        log
+    }
+
+    fn segment_path(dir: &Path, segment: usize) -> PathBuf {
+        let mut bases: Vec<u64> = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| segment::base_offset(&entry.unwrap().path()))
+            .collect();
+
+        bases.sort_unstable();
+        segment::path(dir, bases[segment])
+    }
+
+    fn assert_records(log: &mut Log, offset: u64, count: usize) {
+        let records = log.read(offset, count).unwrap();
+        let expected: Vec<Vec<u8>> = (offset..offset + count as u64).map(record).collect();
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn rolls_over_and_reads_across_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = filled(dir.path(), 10);
+        assert_eq!(log.segments(), 4);
+        assert_eq!(log.next_offset(), 10);
+
+        assert_records(&mut log, 0, 10);
+        assert_records(&mut log, 2, 5);
+        assert_eq!(log.read(8, 5).unwrap().len(), 2);
+        assert!(log.read(10, 1).is_err());
+
+        // Reopening finds every segment again
+        drop(log);
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert_eq!(log.segments(), 4);
+        assert_records(&mut log, 0, 10);
+        assert_eq!(log.append(&record(10)).unwrap(), 10);
+        assert_records(&mut log, 9, 2);
+    }
+
+    #[test]
+    fn parts_make_one_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = filled(dir.path(), 2);
+        let record = record(2);
+        let (head, tail) = record.split_at(3);
+        assert_eq!(log.append_parts(&[head, &[], tail]).unwrap(), 2);
+        log.sync().unwrap();
+        drop(log);
+
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert_records(&mut log, 0, 3);
+    }
+
+    #[test]
+    fn rejects_oversized_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = filled(dir.path(), 2);
+        let error = log.append(&[0; SEGMENT_SIZE as usize]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        drop(log);
+
+        // 4 GiB and more, as parts of the same MiB so that nothing is allocated
+        let chunk = vec![0; 1024 * 1024];
+        let parts = vec![&chunk[..]; 4096];
+        let mut log = Log::open(dir.path(), u64::MAX).unwrap();
+        let error = log.append_parts(&parts).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        log.sync().unwrap();
+        drop(log);
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert_eq!(log.next_offset(), 2);
+        assert_records(&mut log, 0, 2);
+    }
+
+    #[test]
+    fn recovers_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(filled(dir.path(), 5));
+
+        // Header and half of a record, as a crash mid append leaves it
+        let record = record(5);
+        let mut file = OpenOptions::new().append(true).open(segment_path(dir.path(), 1)).unwrap();
+        file.write_all(&segment::header(&[&record])).unwrap();
+        file.write_all(&record[..record.len() / 2]).unwrap();
+        drop(file);
+
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert_eq!(log.next_offset(), 5);
+        assert_eq!(fs::metadata(segment_path(dir.path(), 1)).unwrap().len(), 2 * 18);
+        assert_eq!(log.append(&record).unwrap(), 5);
+        assert_records(&mut log, 0, 6);
+    }
+
+    #[test]
+    fn truncates_at_crc_mismatch_and_drops_later_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(filled(dir.path(), 10));
+
+        // Flip a payload byte of the second record in the second segment
+        let mut file = OpenOptions::new().write(true).open(segment_path(dir.path(), 1)).unwrap();
+        file.seek(SeekFrom::Start(18 + 8)).unwrap();
+        file.write_all(b"X").unwrap();
+        drop(file);
+
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert_eq!(log.next_offset(), 4);
+        assert_eq!(log.segments(), 2);
+        assert_records(&mut log, 0, 4);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn read_before_first_segment_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(filled(dir.path(), 10));
+        fs::remove_file(segment_path(dir.path(), 0)).unwrap();
+
+        let mut log = Log::open(dir.path(), SEGMENT_SIZE).unwrap();
+        assert!(log.read(0, 1).is_err());
+        assert_records(&mut log, 3, 7);
+    }
+}
@@ -0,0 +1,206 @@
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Length and crc
+const HEADER_SIZE: u64 = 8;
+
+pub fn path(dir: &Path, base: u64) -> PathBuf {
+    dir.join(format!("{:020}.segment", base))
+}
+
+/// Base offset of the segment at `path`, if it is one
+pub fn base_offset(path: &Path) -> Option<u64> {
+    if path.extension()? != "segment" {
+        return None;
+    }
+
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Size of the record made up of `parts`, header included
+pub fn record_size(parts: &[&[u8]]) -> u64 {
+    HEADER_SIZE + parts.iter().map(|part| part.len() as u64).sum::<u64>()
+}
+
+fn crc(len: [u8; 4], parts: &[&[u8]]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&len);
+    for part in parts {
+        hasher.update(part);
+    }
+
+    hasher.finalize()
+}
+
+/// Callers check that the length fits in the header first
+pub fn header(parts: &[&[u8]]) -> [u8; HEADER_SIZE as usize] {
+    let len = ((record_size(parts) - HEADER_SIZE) as u32).to_be_bytes();
+    let crc = crc(len, parts).to_be_bytes();
+    let mut header = [0; HEADER_SIZE as usize];
+    header[..4].copy_from_slice(&len);
+    header[4..].copy_from_slice(&crc);
+    header
+}
+
+/// One file of the log. Holds the file position of each of its records,
+/// which serves as the offset index. It's rebuilt by scanning the file when
+/// the log is opened
+pub struct Segment {
+    base: u64,
+    file: BufWriter<File>,
+    size: u64,
+    index: Vec<u64>,
+    dirty: bool,
+}
+
+impl Segment {
+    pub fn create(dir: &Path, base: u64) -> io::Result<Segment> {
+        let file = OpenOptions::new()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .open(path(dir, base))?;
+
+        Ok(Segment {
+            base,
+            file: BufWriter::new(file),
+            size: 0,
+            index: Vec::new(),
+            dirty: false,
+        })
+    }
+
+    /// Opens an existing segment and validates every record. The file is
+    /// truncated at the first torn or corrupted record, in which case this
+    /// also returns false
+    pub fn recover(dir: &Path, base: u64) -> io::Result<(Segment, bool)> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path(dir, base))?;
+        let len = file.metadata()?.len();
+
+        let mut index = Vec::new();
+        let mut position = 0;
+        let mut reader = BufReader::new(&mut file);
+        let mut record = Vec::new();
+        loop {
+            let mut header = [0; HEADER_SIZE as usize];
+            if position + HEADER_SIZE > len {
+                break;
+            }
+
+            reader.read_exact(&mut header)?;
+            let size = u32::from_be_bytes(header[..4].try_into().unwrap());
+            let expected = u32::from_be_bytes(header[4..].try_into().unwrap());
+            if position + HEADER_SIZE + size as u64 > len {
+                break;
+            }
+
+            record.resize(size as usize, 0);
+            reader.read_exact(&mut record)?;
+            if crc(header[..4].try_into().unwrap(), &[&record]) != expected {
+                break;
+            }
+
+            index.push(position);
+            position += HEADER_SIZE + size as u64;
+        }
+
+        let complete = position == len;
+        if !complete {
+            file.set_len(position)?;
+            file.sync_all()?;
+        }
+
+        file.seek(SeekFrom::End(0))?;
+        let segment = Segment {
+            base,
+            file: BufWriter::new(file),
+            size: position,
+            index,
+            dirty: false,
+        };
+
+        Ok((segment, complete))
+    }
+
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn next_offset(&self) -> u64 {
+        self.base + self.index.len() as u64
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Size in bytes, including records not written out yet
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Appends one record made up of `parts`. Fails without writing anything
+    /// if the record is too long for its length to fit in the header
+    pub fn append(&mut self, parts: &[&[u8]]) -> io::Result<u64> {
+        let len = record_size(parts) - HEADER_SIZE;
+        if len > u32::MAX as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Record of {} bytes is longer than the {} a header can hold", len, u32::MAX),
+            ));
+        }
+
+        self.file.write_all(&header(parts))?;
+        for part in parts {
+            self.file.write_all(part)?;
+        }
+
+        let offset = self.next_offset();
+        self.index.push(self.size);
+        self.size += record_size(parts);
+        self.dirty = true;
+        Ok(offset)
+    }
+
+    pub fn read(&mut self, offset: u64) -> io::Result<Vec<u8>> {
+        if self.dirty {
+            self.file.flush()?;
+            self.dirty = false;
+        }
+
+        let position = self.index[(offset - self.base) as usize];
+        let file = self.file.get_ref();
+        let mut header = [0; HEADER_SIZE as usize];
+        read_exact_at(file, &mut header, position)?;
+        let size = u32::from_be_bytes(header[..4].try_into().unwrap());
+
+        let mut record = vec![0; size as usize];
+        read_exact_at(file, &mut record, position + HEADER_SIZE)?;
+        Ok(record)
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.dirty = false;
+        self.file.get_ref().sync_data()
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], position: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    file.read_exact_at(buf, position)
+}
+
+/// Reads with the shared cursor, putting it back at the end for appends
+#[cfg(not(unix))]
+fn read_exact_at(mut file: &File, buf: &mut [u8], position: u64) -> io::Result<()> {
+    file.seek(SeekFrom::Start(position))?;
+    let read = file.read_exact(buf);
+    file.seek(SeekFrom::End(0))?;
+    read
+}
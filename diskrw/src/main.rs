@@ -1,43 +1,154 @@
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use argh::FromArgs;
+use tempfile::Builder;
+
+mod aligned;
+mod commitlog;
+mod fstype;
+mod mmap;
+mod parallel;
+mod payload;
+mod read;
+#[cfg(feature = "uring")]
+mod uring;
+mod write;
+
+use write::Fsync;
 
 #[derive(FromArgs)]
 /// Reach new heights.
 struct Config {
-    /// size of payload
-    #[argh(option, short = 'p', default = "1073741824")]
+    /// size of each write
+    #[argh(option, short = 'p', default = "1024", from_str_fn(common::nonzero))]
     payload_size: usize,
 
-    /// number of messages
-    #[argh(option, short = 'n', default = "10000")]
-    count: u16,
+    /// number of writes
+    #[argh(option, short = 'n', default = "1048576", from_str_fn(common::nonzero))]
+    count: usize,
+
+    /// what to measure. write, read, mmap, uring, log or parallel
+    #[argh(option, short = 'm', default = "Mode::Write")]
+    mode: Mode,
+
+    /// size of each read in read mode
+    #[argh(option, short = 'b', default = "1048576", from_str_fn(common::nonzero))]
+    block_size: usize,
+
+    /// when to fsync. never, always, writes:N or bytes:N
+    #[argh(option, short = 's', default = "Fsync::Never")]
+    fsync: Fsync,
+
+    /// fill payloads with a compressible pattern instead of random bytes
+    #[argh(switch)]
+    compressible: bool,
+
+    /// write with O_DIRECT, bypassing the page cache
+    #[argh(switch)]
+    direct: bool,
+
+    /// write with O_DSYNC, making each write durable before it returns
+    #[argh(switch)]
+    dsync: bool,
+
+    /// directory to create the file in
+    #[argh(option, short = 'd', default = "PathBuf::from(\"/tmp\")")]
+    dir: PathBuf,
+
+    /// keep the file, or the directory in log and parallel modes, after the run
+    #[argh(switch, short = 'k')]
+    keep: bool,
+
+    /// maximum size of a segment in log mode
+    #[argh(option, default = "104857600")]
+    segment_size: u64,
+
+    /// writer threads in parallel mode, each doing `count` writes
+    #[argh(option, short = 't', default = "4")]
+    threads: usize,
+
+    /// have writers in parallel mode share one file instead of owning one each
+    #[argh(switch)]
+    shared: bool,
+
+    /// io_uring operations kept in flight in uring mode
+    #[cfg(feature = "uring")]
+    #[argh(option, default = "32", from_str_fn(uring::queue_depth))]
+    queue_depth: usize,
+
+    /// write from buffers registered with io_uring in uring mode
+    #[cfg(feature = "uring")]
+    #[argh(switch)]
+    registered: bool,
+}
+
+enum Mode {
+    Write,
+    Read,
+    Mmap,
+    #[cfg(feature = "uring")]
+    Uring,
+    Log,
+    Parallel,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(Mode::Write),
+            "read" => Ok(Mode::Read),
+            "mmap" => Ok(Mode::Mmap),
+            #[cfg(feature = "uring")]
+            "uring" => Ok(Mode::Uring),
+            #[cfg(not(feature = "uring"))]
+            "uring" => Err("Built without io_uring. Enable the uring feature".to_owned()),
+            "log" => Ok(Mode::Log),
+            "parallel" => Ok(Mode::Parallel),
+            mode => Err(format!("Unknown mode {}. Expected write, read, mmap, uring, log or parallel", mode)),
+        }
+    }
 }
+
 fn main() {
     let config: Config = argh::from_env();
-    let file_name = "/tmp/napkin.txt";
-
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(file_name)
-        .unwrap();
-
-
-    let start = Instant::now();
-    let payload = common::generate_payload(config.payload_size);
-    println!("generating data ............ took {:?} seconds", start.elapsed().as_secs());
-    let start = Instant::now();
-    file.write_all(&payload).unwrap();
-    file.sync_data().unwrap();
-    
-    let elapsed = start.elapsed();
-    let throughput = config.payload_size as u128 / elapsed.as_millis();
-    let throughput_secs = throughput * 1000;
-    let throughput_secs_mb = throughput_secs / 1024 / 1024;
-
-    fs::remove_file(file_name).unwrap();
-    println!("throughput = {} MB/s", throughput_secs_mb);
+    let fstype = fstype::init(&config.dir);
+    println!("dir = {}, filesystem = {}", config.dir.display(), fstype);
+    fstype::warn(fstype);
+
+    // Unique names so that concurrent runs don't clobber each other
+    let mut builder = Builder::new();
+    builder.prefix("napkin");
+    if let Mode::Log | Mode::Parallel = config.mode {
+        let dir = builder.tempdir_in(&config.dir).unwrap();
+        run(&config, dir.path());
+        if config.keep {
+            println!("kept {}", dir.keep().display());
+        }
+    } else {
+        let file = builder.tempfile_in(&config.dir).unwrap().into_temp_path();
+        run(&config, &file);
+        if config.keep {
+            println!("kept {}", file.keep().unwrap().display());
+        }
+    }
+}
+
+fn run(config: &Config, path: &Path) {
+    match config.mode {
+        Mode::Write => write::write(config, path),
+        Mode::Read => read::read(config, path),
+        Mode::Mmap => mmap::mmap(config, path),
+        #[cfg(feature = "uring")]
+        Mode::Uring => uring::uring(config, path),
+        Mode::Log => commitlog::commitlog(config, path),
+        Mode::Parallel => parallel::parallel(config, path),
+    }
+}
+
+/// MB/s
+fn throughput(size: usize, elapsed: Duration) -> f64 {
+    size as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
 }
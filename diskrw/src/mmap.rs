@@ -0,0 +1,111 @@
+use std::fs::{File, OpenOptions};
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::fstype;
+use crate::payload::Payload;
+use crate::read::drop_cache;
+use crate::write::{self, report, Timings};
+use crate::{throughput, Config};
+
+/// Writes and reads the file through a memory map, after doing the same
+/// writes with `write_all` + `sync_data` so that both are reported together.
+/// Syncs follow the fsync policy with `msync` on the range written since the
+/// previous one
+pub fn mmap(config: &Config, file_name: &Path) {
+    let size = config.payload_size * config.count;
+
+    let start = Instant::now();
+    let payload = Payload::new(config);
+    println!("generating data ............ took {:?}", start.elapsed());
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(file_name)
+        .unwrap();
+
+    let timings = write::write_chunks(config, &mut file, &payload);
+    report("write_all + sync_data", size, &timings);
+
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open(file_name)
+        .unwrap();
+
+    file.set_len(size as u64).unwrap();
+    let mut mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+    let timings = write_chunks(config, &mut mmap, &payload);
+    report("mmap + msync", size, &timings);
+    drop(mmap);
+
+    drop_cache(&file);
+    let (size, elapsed) = read_all(&file, config.block_size);
+    println!("mmap cold read throughput = {:.2} MB/s, filesystem = {}", throughput(size, elapsed), fstype::current());
+
+    let (size, elapsed) = read_all(&file, config.block_size);
+    println!("mmap warm read throughput = {:.2} MB/s, filesystem = {}", throughput(size, elapsed), fstype::current());
+}
+
+fn write_chunks(config: &Config, mmap: &mut MmapMut, payload: &Payload) -> Timings {
+    let mut timings = Timings::new();
+    let mut writes = 0;
+    let mut bytes = 0;
+    let mut synced = 0;
+    let mut offset = 0;
+
+    let start = Instant::now();
+    for _ in 0..config.count {
+        let write_start = Instant::now();
+        for chunk in payload.chunks() {
+            mmap[offset..offset + chunk.len()].copy_from_slice(chunk);
+            offset += chunk.len();
+        }
+
+        timings.writes.record(write_start.elapsed());
+        writes += 1;
+        bytes += payload.size();
+
+        if config.fsync.due(writes, bytes) {
+            timings.syncs.record(msync(mmap, synced, offset));
+            synced = offset;
+            writes = 0;
+            bytes = 0;
+        }
+    }
+
+    if writes > 0 {
+        timings.syncs.record(msync(mmap, synced, offset));
+    }
+
+    timings.elapsed = start.elapsed();
+    timings
+}
+
+fn msync(mmap: &MmapMut, from: usize, to: usize) -> Duration {
+    let start = Instant::now();
+    mmap.flush_range(from, to - from).unwrap();
+    start.elapsed()
+}
+
+/// Copies the mapped file out in `block_size` chunks, like a read into a
+/// buffer would. Includes the time to map the file
+fn read_all(file: &File, block_size: usize) -> (usize, Duration) {
+    let mut buf = vec![0; block_size];
+
+    let start = Instant::now();
+    let mmap = unsafe { Mmap::map(file).unwrap() };
+    for chunk in mmap.chunks(block_size) {
+        buf[..chunk.len()].copy_from_slice(chunk);
+        black_box(&mut buf);
+    }
+
+    (mmap.len(), start.elapsed())
+}
@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::thread;
+use std::time::Instant;
+
+use crate::aligned::ALIGN;
+use crate::payload::Payload;
+use crate::write::{self, label, report, sync, Timings};
+use crate::Config;
+
+/// Runs `threads` writers at once, each doing `count` writes of
+/// `payload_size` with its own fsync policy. Writers either own a file each,
+/// like partitions of a broker, or share one file and `pwrite` to disjoint
+/// ranges of it
+pub fn parallel(config: &Config, dir: &Path) {
+    if config.direct && !config.payload_size.is_multiple_of(ALIGN) {
+        panic!("Direct io needs payload size to be a multiple of {}", ALIGN);
+    }
+
+    let start = Instant::now();
+    let payload = Payload::new(config);
+    println!("generating data ............ took {:?}", start.elapsed());
+
+    let shared = if config.shared {
+        Some(write::open(config, &dir.join("shared")))
+    } else {
+        None
+    };
+
+    let start = Instant::now();
+    let writers: Vec<Timings> = thread::scope(|s| {
+        let handles: Vec<_> = (0..config.threads)
+            .map(|i| {
+                let payload = &payload;
+                let shared = shared.as_ref();
+                s.spawn(move || match shared {
+                    Some(file) => write_at(config, file, payload, i),
+                    None => {
+                        let mut file = write::open(config, &dir.join(format!("writer{}", i)));
+                        write::write_chunks(config, &mut file, payload)
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut all = Timings::new();
+    all.elapsed = start.elapsed();
+    let size = config.payload_size * config.count;
+    for (i, timings) in writers.iter().enumerate() {
+        all.writes.add(&timings.writes);
+        all.syncs.add(&timings.syncs);
+        report(&format!("writer {}", i), size, timings);
+    }
+
+    let label = format!(
+        "{} {} writers{}",
+        label(config),
+        config.threads,
+        if config.shared { " shared" } else { "" }
+    );
+
+    report(&label, size * config.threads, &all);
+}
+
+/// `write::write_chunks` for writer `i` of a shared file. Each writer owns a
+/// contiguous range of `count` payloads
+fn write_at(config: &Config, file: &File, payload: &Payload, i: usize) -> Timings {
+    let mut timings = Timings::new();
+    let mut writes = 0;
+    let mut bytes = 0;
+    let mut offset = (i * config.count * payload.size()) as u64;
+
+    let start = Instant::now();
+    for _ in 0..config.count {
+        let write_start = Instant::now();
+        for chunk in payload.chunks() {
+            file.write_all_at(chunk, offset).unwrap();
+            offset += chunk.len() as u64;
+        }
+
+        timings.writes.record(write_start.elapsed());
+        writes += 1;
+        bytes += payload.size();
+
+        if config.fsync.due(writes, bytes) {
+            timings.syncs.record(sync(file));
+            writes = 0;
+            bytes = 0;
+        }
+    }
+
+    if writes > 0 {
+        timings.syncs.record(sync(file));
+    }
+
+    timings.elapsed = start.elapsed();
+    timings
+}
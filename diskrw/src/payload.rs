@@ -0,0 +1,55 @@
+use common::payload::{Fill, Generator};
+
+use crate::aligned::AlignedBuf;
+use crate::Config;
+
+/// Most memory held for a payload. Larger payloads are written as several
+/// chunks of the same buffer
+pub const CHUNK: usize = 4 * 1024 * 1024;
+
+/// One write of `payload_size` bytes backed by a buffer of at most `CHUNK`,
+/// generated once. The buffer is aligned so that chunks are usable with
+/// `O_DIRECT`. Every write repeats the buffer, as do the chunks of payloads
+/// larger than `CHUNK`, so filesystems that dedupe see through anything past
+/// the first buffer. Runs which write more than that are warned
+pub struct Payload {
+    buf: AlignedBuf,
+    size: usize,
+}
+
+impl Payload {
+    pub fn new(config: &Config) -> Payload {
+        let len = config.payload_size.min(CHUNK);
+        if config.payload_size.saturating_mul(config.count) > len {
+            println!(
+                "warning: writes repeat one {} byte buffer. Deduping filesystems store less than is written",
+                len
+            );
+        }
+
+        let mut buf = AlignedBuf::zeroed(len);
+        Generator::new(fill(config)).fill(&mut buf);
+        Payload {
+            buf,
+            size: config.payload_size,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Chunks which together make up the payload
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        let size = self.size;
+        (0..size).step_by(CHUNK).map(move |offset| &self.buf[..(size - offset).min(CHUNK)])
+    }
+}
+
+pub fn fill(config: &Config) -> Fill {
+    if config.compressible {
+        Fill::Compressible
+    } else {
+        Fill::Random
+    }
+}
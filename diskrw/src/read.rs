@@ -0,0 +1,83 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use common::latency::Latencies;
+
+use crate::fstype;
+use crate::payload::Payload;
+use crate::write::percentiles;
+use crate::{throughput, Config};
+
+/// Writes `count` chunks of `payload_size` and reads them back sequentially,
+/// first with the file evicted from the page cache and then again with it
+/// fully cached
+pub fn read(config: &Config, file_name: &Path) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(file_name)
+        .unwrap();
+
+    let start = Instant::now();
+    let payload = Payload::new(config);
+    println!("generating data ............ took {:?}", start.elapsed());
+    for _ in 0..config.count {
+        for chunk in payload.chunks() {
+            file.write_all(chunk).unwrap();
+        }
+    }
+
+    file.sync_data().unwrap();
+    drop(payload);
+
+    let mut file = File::open(file_name).unwrap();
+    drop_cache(&file);
+    let (size, elapsed, reads) = read_all(&mut file, config.block_size);
+    println!("cold read throughput = {:.2} MB/s, filesystem = {}", throughput(size, elapsed), fstype::current());
+    percentiles("cold", "reads", &reads);
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let (size, elapsed, reads) = read_all(&mut file, config.block_size);
+    println!("warm read throughput = {:.2} MB/s, filesystem = {}", throughput(size, elapsed), fstype::current());
+    percentiles("warm", "reads", &reads);
+}
+
+fn read_all(file: &mut File, block_size: usize) -> (usize, Duration, Latencies) {
+    let mut buf = vec![0; block_size];
+    let mut reads = Latencies::new();
+    let mut size = 0;
+
+    let start = Instant::now();
+    loop {
+        let read_start = Instant::now();
+        let n = file.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+
+        reads.record(read_start.elapsed());
+        size += n;
+    }
+
+    (size, start.elapsed(), reads)
+}
+
+/// Evicts the file's pages from the page cache. Only clean pages are dropped,
+/// so the file should be synced before this
+#[cfg(target_os = "linux")]
+pub fn drop_cache(file: &File) {
+    use std::os::unix::io::AsRawFd;
+
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if ret != 0 {
+        panic!("posix_fadvise failed. Error = {}", ret);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn drop_cache(_file: &File) {
+    println!("warning: can't drop page cache on this platform. cold read is warm");
+}
@@ -0,0 +1,157 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::Instant;
+
+use io_uring::squeue::Flags;
+use io_uring::types::{Fd, FsyncFlags};
+use io_uring::{opcode, squeue, IoUring};
+
+use crate::aligned::ALIGN;
+use crate::payload::{Payload, CHUNK};
+use crate::write::{self, label, report, Timings};
+use crate::Config;
+
+/// Marks fsyncs in user data. The rest is the index of the write
+const FSYNC: u64 = 1 << 63;
+
+/// Most entries the kernel sets up a ring with
+const MAX_ENTRIES: usize = 32768;
+
+/// Queue depths a ring with room for a linked fsync per write can hold
+pub fn queue_depth(value: &str) -> Result<usize, String> {
+    match common::nonzero(value)? {
+        depth if depth > MAX_ENTRIES / 2 => Err(format!("should be at most {}", MAX_ENTRIES / 2)),
+        depth => Ok(depth),
+    }
+}
+
+/// Writes `count` chunks of `payload_size` through io_uring, keeping up to
+/// `queue_depth` operations in flight. Fsyncs due as per the fsync policy are
+/// linked to the write which made them due and drain the queue, so that they
+/// cover every write submitted before them
+pub fn uring(config: &Config, file_name: &Path) {
+    if config.direct && !config.payload_size.is_multiple_of(ALIGN) {
+        panic!("Direct io needs payload size to be a multiple of {}", ALIGN);
+    }
+
+    if config.payload_size > CHUNK {
+        panic!("Uring mode writes each payload in one operation. Payload size can be at most {}", CHUNK);
+    }
+
+    let file = write::open(config, file_name);
+    let fd = Fd(file.as_raw_fd());
+
+    let start = Instant::now();
+    let payload = Payload::new(config);
+    let payload = payload.chunks().next().unwrap_or_default();
+    println!("generating data ............ took {:?}", start.elapsed());
+
+    // Room for a linked fsync with every write
+    let entries = 2 * config.queue_depth as u32;
+    let mut ring = match IoUring::new(entries) {
+        Ok(ring) => ring,
+        Err(e) => panic!("Can't set up io_uring with {} entries. {}", entries, e),
+    };
+    if config.registered {
+        let iovec = libc::iovec {
+            iov_base: payload.as_ptr() as *mut _,
+            iov_len: payload.len(),
+        };
+
+        ring.submitter().register_buffers(&[iovec]).unwrap();
+    }
+
+    let write_entry = |i: usize| {
+        let offset = (i * config.payload_size) as i64;
+        let entry = if config.registered {
+            opcode::WriteFixed::new(fd, payload.as_ptr(), payload.len() as u32, 0)
+                .offset64(offset)
+                .build()
+        } else {
+            opcode::Write::new(fd, payload.as_ptr(), payload.len() as u32)
+                .offset64(offset)
+                .build()
+        };
+
+        entry.user_data(i as u64)
+    };
+
+    let fsync_entry = |i: usize| {
+        opcode::Fsync::new(fd)
+            .flags(FsyncFlags::DATASYNC)
+            .build()
+            .flags(Flags::IO_DRAIN)
+            .user_data(FSYNC | i as u64)
+    };
+
+    // Submission time of each write and of the fsync following it
+    let mut submitted = vec![None; config.count];
+    let mut fsync_submitted = vec![None; config.count];
+    let mut timings = Timings::new();
+
+    let mut next = 0;
+    let mut inflight = 0;
+    let mut unsynced_writes = 0;
+    let mut unsynced_bytes = 0;
+
+    let start = Instant::now();
+    while next < config.count || inflight > 0 {
+        while inflight < config.queue_depth && next < config.count {
+            unsynced_writes += 1;
+            unsynced_bytes += config.payload_size;
+
+            // The last write is always followed by a sync
+            let due = next + 1 == config.count || config.fsync.due(unsynced_writes, unsynced_bytes);
+            let mut sq = ring.submission();
+            let now = Instant::now();
+            if due {
+                let entries = [write_entry(next).flags(Flags::IO_LINK), fsync_entry(next)];
+                push(&mut sq, &entries);
+                fsync_submitted[next] = Some(now);
+                unsynced_writes = 0;
+                unsynced_bytes = 0;
+                inflight += 2;
+            } else {
+                push(&mut sq, &[write_entry(next)]);
+                inflight += 1;
+            }
+
+            submitted[next] = Some(now);
+            next += 1;
+        }
+
+        ring.submit_and_wait(1).unwrap();
+        for cqe in ring.completion() {
+            let now = Instant::now();
+            let user_data = cqe.user_data();
+            let result = cqe.result();
+            if result < 0 {
+                panic!("Operation {} failed. Error = {}", user_data, io::Error::from_raw_os_error(-result));
+            }
+
+            if user_data & FSYNC != 0 {
+                let i = (user_data & !FSYNC) as usize;
+                timings.syncs.record(now - fsync_submitted[i].take().unwrap());
+            } else {
+                if result as usize != config.payload_size {
+                    panic!("Short write. Wrote {} of {} bytes", result, config.payload_size);
+                }
+
+                timings.writes.record(now - submitted[user_data as usize].take().unwrap());
+            }
+
+            inflight -= 1;
+        }
+    }
+
+    timings.elapsed = start.elapsed();
+    let label = format!("uring {} qd={}{}", label(config), config.queue_depth, if config.registered { " registered" } else { "" });
+    report(&label, config.payload_size * config.count, &timings);
+}
+
+fn push(sq: &mut squeue::SubmissionQueue, entries: &[squeue::Entry]) {
+    // Safe as the file and payload outlive the ring's use of them. Every
+    // operation is reaped before returning
+    unsafe { sq.push_multiple(entries).unwrap() }
+}
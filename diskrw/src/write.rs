@@ -0,0 +1,186 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use common::latency::Latencies;
+
+use crate::aligned::ALIGN;
+use crate::fstype;
+use crate::payload::Payload;
+use crate::{throughput, Config};
+
+/// When to `sync_data` while writing. There is always a final sync after the
+/// last write so that every policy ends with the same data on disk
+pub enum Fsync {
+    Never,
+    Always,
+    /// After every N writes
+    Writes(usize),
+    /// Once N bytes have been written since the last sync
+    Bytes(usize),
+}
+
+impl Fsync {
+    pub fn due(&self, writes: usize, bytes: usize) -> bool {
+        match self {
+            Fsync::Never => false,
+            Fsync::Always => true,
+            Fsync::Writes(n) => writes >= *n,
+            Fsync::Bytes(n) => bytes >= *n,
+        }
+    }
+}
+
+impl FromStr for Fsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policy = match s.split_once(':') {
+            None if s == "never" => Fsync::Never,
+            None if s == "always" => Fsync::Always,
+            Some(("writes", n)) => Fsync::Writes(n.parse().map_err(|e| format!("Invalid write count {}. {}", n, e))?),
+            Some(("bytes", n)) => Fsync::Bytes(n.parse().map_err(|e| format!("Invalid byte count {}. {}", n, e))?),
+            _ => return Err(format!("Unknown fsync policy {}. Expected never, always, writes:N or bytes:N", s)),
+        };
+
+        Ok(policy)
+    }
+}
+
+/// Writes `count` chunks of `payload_size` syncing as per the fsync policy
+pub fn write(config: &Config, file_name: &Path) {
+    if config.direct && !config.payload_size.is_multiple_of(ALIGN) {
+        panic!("Direct io needs payload size to be a multiple of {}", ALIGN);
+    }
+
+    let mut file = open(config, file_name);
+
+    let start = Instant::now();
+    let payload = Payload::new(config);
+    println!("generating data ............ took {:?}", start.elapsed());
+
+    let timings = write_chunks(config, &mut file, &payload);
+    report(label(config), config.payload_size * config.count, &timings);
+}
+
+/// Total time of a run of writes and the latency of each write and sync
+pub struct Timings {
+    pub elapsed: Duration,
+    pub writes: Latencies,
+    pub syncs: Latencies,
+}
+
+impl Timings {
+    pub fn new() -> Timings {
+        Timings {
+            elapsed: Duration::default(),
+            writes: Latencies::new(),
+            syncs: Latencies::new(),
+        }
+    }
+}
+
+/// Writes `count` copies of `payload` to the file
+pub fn write_chunks(config: &Config, file: &mut File, payload: &Payload) -> Timings {
+    let mut timings = Timings::new();
+    let mut writes = 0;
+    let mut bytes = 0;
+
+    let start = Instant::now();
+    for _ in 0..config.count {
+        let write_start = Instant::now();
+        for chunk in payload.chunks() {
+            file.write_all(chunk).unwrap();
+        }
+
+        timings.writes.record(write_start.elapsed());
+        writes += 1;
+        bytes += payload.size();
+
+        if config.fsync.due(writes, bytes) {
+            timings.syncs.record(sync(file));
+            writes = 0;
+            bytes = 0;
+        }
+    }
+
+    if writes > 0 {
+        timings.syncs.record(sync(file));
+    }
+
+    timings.elapsed = start.elapsed();
+    timings
+}
+
+pub fn report(label: &str, size: usize, timings: &Timings) {
+    println!("{} throughput = {:.2} MB/s, filesystem = {}", label, throughput(size, timings.elapsed), fstype::current());
+    percentiles(label, "writes", &timings.writes);
+    percentiles(label, "syncs", &timings.syncs);
+}
+
+/// Count and percentiles of a run's operations
+pub fn percentiles(label: &str, what: &str, latencies: &Latencies) {
+    println!(
+        "{} {} = {}, {}, filesystem = {}",
+        label,
+        what,
+        latencies.len(),
+        latencies.percentiles(),
+        fstype::current()
+    );
+}
+
+pub fn label(config: &Config) -> &'static str {
+    match (config.direct, config.dsync) {
+        (false, false) => "buffered",
+        (true, false) => "direct",
+        (false, true) => "dsync",
+        (true, true) => "direct+dsync",
+    }
+}
+
+/// `O_DIRECT` skips the page cache so that timings are of the device and not
+/// of memcpy. `O_DSYNC` makes every write return only once its data is durable
+#[cfg(target_os = "linux")]
+pub fn open(config: &Config, file_name: &Path) -> File {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut flags = 0;
+    if config.direct {
+        flags |= libc::O_DIRECT;
+    }
+
+    if config.dsync {
+        flags |= libc::O_DSYNC;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .custom_flags(flags)
+        .open(file_name)
+        .unwrap()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn open(config: &Config, file_name: &Path) -> File {
+    if config.direct || config.dsync {
+        panic!("Direct and dsync writes are only supported on linux");
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(file_name)
+        .unwrap()
+}
+
+pub fn sync(file: &File) -> Duration {
+    let start = Instant::now();
+    file.sync_data().unwrap();
+    start.elapsed()
+}
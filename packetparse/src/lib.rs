@@ -44,9 +44,18 @@ pub fn assemble(byte1: u8, variable_header_index: usize, mut payload: Bytes) ->
 }
 
 pub fn disassemble(packet: Packet, payload: &mut BytesMut) {
-    payload.reserve(packet.topic.len() + packet.payload.len() + 10);
+    payload.reserve(packet.payload.len());
+    disassemble_header(&packet, packet.payload.len(), payload);
+    payload.put(packet.payload);
+}
+
+/// Everything of the PUBLISH before its payload, for a payload of
+/// `payload_len` bytes in place of `packet.payload`. Lets large payloads be
+/// written out after it in pieces
+pub fn disassemble_header(packet: &Packet, payload_len: usize, payload: &mut BytesMut) {
+    payload.reserve(packet.topic.len() + 10);
     payload.put_u8(0b0011_0000 | packet.retain as u8 | ((packet.qos as u8) << 1) | ((packet.dup as u8) << 3));
-    let mut len = packet.topic.len() + 2 + packet.payload.len();
+    let mut len = packet.topic.len() + 2 + payload_len;
     if packet.qos != 0 && packet.pkid != 0 {
         len += 2;
     }
@@ -61,8 +70,6 @@ pub fn disassemble(packet: Packet, payload: &mut BytesMut) {
 
         payload.put_u16(pkid);
     }
-
-    payload.put(packet.payload);
 }
 
 
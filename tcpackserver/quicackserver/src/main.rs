@@ -12,6 +12,8 @@ use std::io;
 use quinn::{ServerConfig, PrivateKey, TransportConfig, ServerConfigBuilder, Certificate, CertificateChain, ClientConfig, ClientConfigBuilder, Endpoint};
 use std::sync::Arc;
 
+use common::latency::{InOrder, Latencies};
+
 #[derive(FromArgs)]
 /// Reach new heights.
 struct Config {
@@ -78,7 +80,7 @@ async fn server(server_config: ServerConfig) -> Result<(), io::Error> {
     }
 }
 
-async fn client(payload_size: usize, max_count: usize, server_certs: &[&[u8]]) -> Result<(), io::Error> {
+async fn client(payload_size: usize, max_count: usize, server_certs: &[&[u8]]) -> Result<Latencies, io::Error> {
     let client_cfg = configure_client(server_certs).unwrap();
     let mut endpoint_builder = Endpoint::builder();
     endpoint_builder.default_client_config(client_cfg);
@@ -98,25 +100,25 @@ async fn client(payload_size: usize, max_count: usize, server_certs: &[&[u8]]) -
     let stream = stream::iter(stream);
 
     pin!(stream);
-    let mut count = 0;
+    let mut acks = InOrder::new();
 
     let payload = common::generate_string(payload_size) + "\n";
     loop {
         select! {
             Some(_i) = stream.next() => {
+                acks.sent();
                 tx.send(payload.clone()).await.unwrap();
             }
             Some(data) = rx.next() => {
                 let _data = data?;
-                 count += 1;
-                 if count >= max_count {
+                 if acks.acked() >= max_count {
                     break
                  }
             }
         }
     }
 
-    Ok(())
+    Ok(acks.rtts())
 }
 
 #[tokio::main(core_threads = 2)]
@@ -132,12 +134,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     tokio::time::delay_for(Duration::from_millis(1)).await;
     let start = Instant::now();
-    client(payload_size, count, &[&server_cert]).await.unwrap();
+    let rtts = client(payload_size, count, &[&server_cert]).await.unwrap();
 
     let elapsed = start.elapsed();
     let throughput = (config.payload_size * config.count as usize) as u128 / elapsed.as_millis();
     let throughput_secs = throughput * 1000;
     let throughput_secs_mb = throughput_secs / 1024 / 1024;
     println!("throughput = {} MB/s", throughput_secs_mb);
+    rtts.print("ack rtt");
     Ok(())
 }
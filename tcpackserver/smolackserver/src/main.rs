@@ -4,6 +4,8 @@ use std::error::Error;
 use std::net::{TcpListener, TcpStream};
 use std::time::Instant;
 
+use common::latency::{InOrder, Latencies};
+
 use futures_codec::{Framed, LinesCodec};
 use futures_util::future;
 use futures_util::stream;
@@ -39,31 +41,31 @@ async fn server() -> Result<(), io::Error> {
     }
 }
 
-async fn client(payload_size: usize, max_count: usize) -> Result<(), io::Error> {
+async fn client(payload_size: usize, max_count: usize) -> Result<Latencies, io::Error> {
     let socket = Async::<TcpStream>::connect("127.0.0.1:8080").await?;
     let frames = Framed::new(socket, LinesCodec);
     let stream: Vec<usize> = (0..max_count).collect();
     let stream = stream::iter(stream);
 
-    let mut count = 0;
+    let mut acks = InOrder::new();
     let mut frames = frames.fuse();
     let mut stream = stream.fuse();
     let payload = common::generate_string(payload_size) + "\n";
     loop {
         select! {
             Some(_) = stream.next() => {
+                acks.sent();
                 frames.send(payload.clone()).await?;
             }
             Some(o) = frames.next() => {
-                count += 1;
-                if count >= max_count {
+                if acks.acked() >= max_count {
                     break;
                 }
             }
         }
     }
 
-    Ok(())
+    Ok(acks.rtts())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -80,7 +82,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         let _server = Task::spawn(server());
 
         let start = Instant::now();
-        client(payload_size, count).await.unwrap();
+        let rtts = client(payload_size, count).await.unwrap();
 
         let elapsed = start.elapsed();
         let throughput =
@@ -89,6 +91,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         let throughput_secs_mb = throughput_secs / 1024 / 1024;
 
         println!("throughput = {} MB/s", throughput_secs_mb);
+        rtts.print("ack rtt");
     });
     Ok(())
 }
@@ -1,4 +1,8 @@
+mod mqtt;
+
 use argh::FromArgs;
+use bytes::Bytes;
+use packetparse::Packet;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::pin;
@@ -13,6 +17,11 @@ use futures_util::SinkExt;
 use std::error::Error;
 use std::time::{Instant, Duration};
 use std::io;
+use std::str::FromStr;
+
+use common::latency::{InOrder, Latencies};
+
+use mqtt::{Frame, MqttCodec};
 
 #[derive(FromArgs)]
 /// Reach new heights.
@@ -24,23 +33,60 @@ struct Config {
     /// number of messages
     #[argh(option, short = 'n', default = "10000")]
     count: usize,
+
+    /// wire protocol: lines or mqtt
+    #[argh(option, default = "Protocol::Lines")]
+    protocol: Protocol,
+
+    /// unacked publishes the mqtt client allows
+    #[argh(option, default = "100", from_str_fn(common::nonzero))]
+    inflight: u16,
 }
 
-async fn server() -> Result<(), io::Error> {
+#[derive(Clone, Copy)]
+enum Protocol {
+    Lines,
+    Mqtt,
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Protocol, String> {
+        match s {
+            "lines" => Ok(Protocol::Lines),
+            "mqtt" => Ok(Protocol::Mqtt),
+            _ => Err(format!("unknown protocol {}. Expected lines or mqtt", s)),
+        }
+    }
+}
+
+async fn server(protocol: Protocol) -> Result<(), io::Error> {
     let mut listener = TcpListener::bind("127.0.0.1:8080").await?;
 
     loop {
         let (socket, _) = listener.accept().await?;
-        task::spawn(async move {
-            let mut frames = Framed::new(socket, LinesCodec::new());
-            while let Some(_line) = frames.next().await {
-                frames.send("ack".to_owned()).await.unwrap();
-            }
-        });
+        match protocol {
+            Protocol::Lines => task::spawn(async move {
+                let mut frames = Framed::new(socket, LinesCodec::new());
+                while let Some(_line) = frames.next().await {
+                    frames.send("ack".to_owned()).await.unwrap();
+                }
+            }),
+            Protocol::Mqtt => task::spawn(async move {
+                let mut frames = Framed::new(socket, MqttCodec);
+                while let Some(frame) = frames.next().await {
+                    match frame.unwrap() {
+                        Frame::Publish(publish) => frames.send(Frame::PubAck(publish.pkid)).await.unwrap(),
+                        Frame::PubAck(pkid) => panic!("Unexpected puback from client. pkid = {}", pkid),
+                    }
+                }
+            }),
+        };
     }
 }
 
-async fn client(payload_size: usize, max_count: usize) -> Result<(), io::Error> {
+async fn client(payload_size: usize, max_count: usize) -> Result<Latencies, io::Error> {
     let socket = TcpStream::connect("127.0.0.1:8080").await.unwrap();
     let mut frames = Framed::new(socket, LinesCodec::new());
 
@@ -48,24 +94,87 @@ async fn client(payload_size: usize, max_count: usize) -> Result<(), io::Error>
     let stream = stream::iter(stream);
 
     pin!(stream);
-    let mut count = 0;
+    let mut acks = InOrder::new();
     let payload = common::generate_string(payload_size);
 
     loop {
         select! {
             Some(_) = stream.next() => {
+                acks.sent();
                 frames.send(payload.clone()).await.unwrap();
             }
             Some(_data) = frames.next() => {
-                 count += 1;
-                 if count >= max_count {
+                 if acks.acked() >= max_count {
                     break;
                  }
             }
         }
     }
 
-    Ok(())
+    Ok(acks.rtts())
+}
+
+/// Publishes with qos 1 and matches pubacks to the publishes still in flight
+async fn mqtt_client(payload_size: usize, max_count: usize, max_inflight: u16) -> Result<Latencies, io::Error> {
+    let socket = TcpStream::connect("127.0.0.1:8080").await.unwrap();
+    let mut frames = Framed::new(socket, MqttCodec);
+
+    let stream: Vec<usize> = (0..max_count).collect();
+    let stream = stream::iter(stream);
+
+    pin!(stream);
+    let mut count = 0;
+    let payload = Bytes::from(common::generate_payload(payload_size));
+
+    // Send times indexed by pkid. Ids are reused once acked, so inflight has
+    // to stay below the 65535 ids there are
+    let mut outstanding = vec![None; u16::MAX as usize + 1];
+    let mut rtts = Latencies::new();
+    let mut inflight = 0;
+    let mut pkid = 0;
+
+    loop {
+        select! {
+            Some(_) = stream.next(), if inflight < max_inflight => {
+                pkid = pkid % u16::MAX + 1;
+                if outstanding[pkid as usize].is_some() {
+                    panic!("pkid {} is still in flight", pkid);
+                }
+
+                let publish = Packet {
+                    topic: "hello/mqtt/ack".to_owned(),
+                    dup: false,
+                    retain: false,
+                    qos: 1,
+                    pkid,
+                    payload: payload.clone(),
+                };
+
+                outstanding[pkid as usize] = Some(Instant::now());
+                inflight += 1;
+                frames.send(Frame::Publish(publish)).await.unwrap();
+            }
+            Some(frame) = frames.next() => {
+                let acked = match frame? {
+                    Frame::PubAck(pkid) => pkid,
+                    Frame::Publish(publish) => panic!("Unexpected publish from server. pkid = {}", publish.pkid),
+                };
+
+                match outstanding[acked as usize].take() {
+                    Some(sent) => rtts.record(sent.elapsed()),
+                    None => panic!("Unexpected puback. pkid = {}", acked),
+                }
+
+                inflight -= 1;
+                count += 1;
+                if count >= max_count {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(rtts)
 }
 
 #[tokio::main(core_threads = 2)]
@@ -73,19 +182,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let config: Config = argh::from_env();
     let count = config.count;
     let payload_size = config.payload_size;
+    let protocol = config.protocol;
 
     task::spawn(async move {
-        server().await.unwrap();
+        server(protocol).await.unwrap();
     });
 
     tokio::time::delay_for(Duration::from_millis(1)).await;
     let start = Instant::now();
-    client(payload_size, count).await.unwrap();
+    let rtts = match protocol {
+        Protocol::Lines => client(payload_size, count).await.unwrap(),
+        Protocol::Mqtt => mqtt_client(payload_size, count, config.inflight).await.unwrap(),
+    };
 
     let elapsed = start.elapsed();
     let throughput = (config.payload_size * config.count as usize) as u128 / elapsed.as_millis();
     let throughput_secs = throughput * 1000;
     let throughput_secs_mb = throughput_secs / 1024 / 1024;
     println!("throughput = {} MB/s", throughput_secs_mb);
+    rtts.print("ack rtt");
     Ok(())
 }
@@ -0,0 +1,168 @@
+//! Just enough of mqtt to publish with qos 1 and get acked like a broker would
+use bytes::{Buf, BufMut, BytesMut};
+use packetparse::common::{header_len, parse_fixed_header};
+use packetparse::Packet;
+use tokio_util::codec::{Decoder, Encoder};
+
+use std::io;
+
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+
+pub enum Frame {
+    Publish(Packet),
+    PubAck(u16),
+}
+
+pub struct MqttCodec;
+
+/// Length of the first packet in the stream. `None` till the whole fixed
+/// header is in. Remaining length takes at most 4 bytes, so a 4th byte that
+/// still says there's more is malformed
+fn packet_len(stream: &[u8]) -> Result<Option<usize>, io::Error> {
+    let length = stream.iter().skip(1).take(4);
+    if !length.clone().any(|byte| byte & 0x80 == 0) {
+        if length.count() == 4 {
+            let error = "Malformed remaining length";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+
+        return Ok(None);
+    }
+
+    let (_, remaining_len) = parse_fixed_header(stream);
+    Ok(Some(header_len(remaining_len) + remaining_len))
+}
+
+impl Decoder for MqttCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, io::Error> {
+        let len = match packet_len(src)? {
+            Some(len) if src.len() >= len => len,
+            Some(len) => {
+                src.reserve(len - src.len());
+                return Ok(None);
+            }
+            None => return Ok(None),
+        };
+
+        match src[0] >> 4 {
+            PUBLISH => Ok(Some(Frame::Publish(packetparse::next_packet(src)))),
+            PUBACK if len == 4 => {
+                let mut puback = src.split_to(len);
+                puback.advance(2);
+                Ok(Some(Frame::PubAck(puback.get_u16())))
+            }
+            kind => {
+                let error = format!("Unexpected packet type = {}, len = {}", kind, len);
+                Err(io::Error::new(io::ErrorKind::InvalidData, error))
+            }
+        }
+    }
+}
+
+impl Encoder<Frame> for MqttCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> Result<(), io::Error> {
+        match frame {
+            Frame::Publish(publish) => packetparse::disassemble(publish, dst),
+            Frame::PubAck(pkid) => {
+                dst.reserve(4);
+                dst.put_u8(PUBACK << 4);
+                dst.put_u8(2);
+                dst.put_u16(pkid);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::Bytes;
+
+    fn publish(pkid: u16, size: usize) -> Packet {
+        Packet {
+            topic: "hello/mqtt/ack".to_owned(),
+            dup: false,
+            retain: false,
+            qos: 1,
+            pkid,
+            payload: Bytes::from(vec![7; size]),
+        }
+    }
+
+    fn encode(frames: Vec<Frame>) -> BytesMut {
+        let mut stream = BytesMut::new();
+        for frame in frames {
+            MqttCodec.encode(frame, &mut stream).unwrap();
+        }
+
+        stream
+    }
+
+    #[test]
+    fn publish_round_trips() {
+        // 200 bytes takes 2 bytes of remaining length
+        for &size in &[0, 10, 200] {
+            let mut stream = encode(vec![Frame::Publish(publish(7, size))]);
+            match MqttCodec.decode(&mut stream).unwrap() {
+                Some(Frame::Publish(publish)) => {
+                    assert_eq!(publish.topic, "hello/mqtt/ack");
+                    assert_eq!(publish.qos, 1);
+                    assert_eq!(publish.pkid, 7);
+                    assert_eq!(publish.payload, Bytes::from(vec![7; size]));
+                }
+                _ => panic!("Expected a publish of {} bytes", size),
+            }
+
+            assert!(stream.is_empty());
+        }
+    }
+
+    #[test]
+    fn puback_round_trips() {
+        let mut stream = encode(vec![Frame::PubAck(300)]);
+        assert_eq!(&stream[..], &[0x40, 2, 1, 44]);
+        match MqttCodec.decode(&mut stream).unwrap() {
+            Some(Frame::PubAck(pkid)) => assert_eq!(pkid, 300),
+            _ => panic!("Expected a puback"),
+        }
+
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn partial_frames_wait_for_the_rest() {
+        let frames = encode(vec![Frame::Publish(publish(1, 200)), Frame::PubAck(1)]);
+        let mut stream = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in frames.iter() {
+            stream.extend_from_slice(&[*byte]);
+            while let Some(frame) = MqttCodec.decode(&mut stream).unwrap() {
+                decoded.push(frame);
+            }
+        }
+
+        assert!(stream.is_empty());
+        match &decoded[..] {
+            [Frame::Publish(publish), Frame::PubAck(1)] => assert_eq!(publish.payload.len(), 200),
+            _ => panic!("Expected a publish and a puback"),
+        }
+    }
+
+    #[test]
+    fn malformed_length_fails() {
+        let mut stream = BytesMut::from(&[0x30, 0xff, 0xff, 0xff][..]);
+        assert!(MqttCodec.decode(&mut stream).unwrap().is_none());
+
+        stream.extend_from_slice(&[0xff]);
+        let error = MqttCodec.decode(&mut stream).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}